@@ -0,0 +1,116 @@
+//! Times `Script::binary_serialize` on large synthetic scripts, and fails if serializing stops
+//! being linear in the size of the script. Run with `cargo bench`.
+
+use std::time::{Duration, Instant};
+
+use yeti::opcodescript::{InsertOpcode, Opcode, Quirks, Script};
+
+/// How many times each script is serialized. The fastest run is reported.
+const RUNS: usize = 5;
+
+/// How much slower per opcode the largest script may serialize than the smallest. The largest
+/// is 100 times the size, so going back to scanning the script for every jump would be about 100
+/// times slower, while cache effects stay well below this.
+const MAX_SLOWDOWN: f64 = 10.0;
+
+/// A script of `lines` lines of text, each followed by a wait and a conditional jump back to the
+/// line before it, so there's a jump to resolve for every third opcode.
+fn synthetic_script(lines: usize) -> Script {
+	let mut data = vec![0x04, 0x00, 0x00, 0x00];
+	let mut previous_line = 4u32;
+	for _ in 0..lines {
+		let line = data.len() as u32;
+		data.extend([0x45, 0xFF, 0xFF, 0x14, 0x00, b'X', 0x00]);
+		data.extend([0x4A, 0xFF, 0xFF]);
+		data.extend([0x06, 0x01, 0x00, 0x00, 0x00]);
+		data.extend(previous_line.to_le_bytes());
+		previous_line = line;
+	}
+	data.extend([0x05, 0x00]);
+
+	let (script, error) = Script::new(&data, Quirks::empty()).unwrap();
+	assert!(error.is_none(), "The synthetic script didn't disassemble.");
+	assert_eq!(script.opcodes.len(), lines * 3 + 1);
+	script
+}
+
+/// The same script with a new line inserted after every tenth line. The inserted lines have
+/// addresses of their own, and every tenth jump goes to one of them.
+fn synthetic_script_with_inserts(lines: usize) -> Script {
+	let mut script = synthetic_script(lines);
+	let mut opcodes = Vec::with_capacity(script.opcodes.len() + lines / 10);
+	for (idx, opcode) in script.opcodes.into_iter().enumerate() {
+		let line = idx / 3;
+		if idx % 3 == 0 && line % 10 == 0 && line < lines {
+			let Opcode::OP_TEXTBOX_DISPLAY(mut inserted) = opcode.clone() else {
+				unreachable!()
+			};
+			inserted.address = 0x0100_0000 + line as u32;
+			opcodes.push(Opcode::OP_Insert(InsertOpcode {
+				contents: vec![Opcode::OP_TEXTBOX_DISPLAY(inserted)],
+			}));
+		}
+		if let Opcode::JNE(jump) | Opcode::JE(jump) = &opcode {
+			if line % 10 == 1 {
+				let mut jump = jump.clone();
+				jump.jump_address = 0x0100_0000 + line as u32 - 1;
+				opcodes.push(jump.into());
+				continue;
+			}
+		}
+		opcodes.push(opcode);
+	}
+	script.opcodes = opcodes;
+	assert!(script.validate().is_empty());
+	script
+}
+
+fn time(script: &Script) -> (Duration, usize) {
+	let mut best = Duration::MAX;
+	let mut size = 0;
+	for _ in 0..RUNS {
+		let start = Instant::now();
+		size = script.binary_serialize().len();
+		best = best.min(start.elapsed());
+	}
+	(best, size)
+}
+
+fn main() {
+	println!(
+		"{:<14} {:>10} {:>12} {:>12} {:>14}",
+		"script", "opcodes", "bytes", "time", "per opcode"
+	);
+	let sizes = [1_000, 10_000, 100_000];
+	let mut per_opcode: Vec<(&str, f64, f64)> = vec![];
+	for lines in sizes {
+		for (name, script) in [
+			("plain", synthetic_script(lines)),
+			("with inserts", synthetic_script_with_inserts(lines)),
+		] {
+			let (elapsed, size) = time(&script);
+			let nanos = elapsed.as_secs_f64() * 1e9 / script.opcodes.len() as f64;
+			println!(
+				"{name:<14} {:>10} {size:>12} {:>10.2}ms {nanos:>12.1}ns",
+				script.opcodes.len(),
+				elapsed.as_secs_f64() * 1000.0,
+			);
+
+			match per_opcode.iter_mut().find(|(it, _, _)| *it == name) {
+				Some((_, _, largest)) => *largest = nanos,
+				None => per_opcode.push((name, nanos, nanos)),
+			}
+		}
+	}
+
+	for (name, smallest, largest) in per_opcode {
+		let slowdown = largest / smallest;
+		assert!(
+			slowdown < MAX_SLOWDOWN,
+			"Serializing the {name} script is {slowdown:.1} times slower per opcode at {} lines \
+			 than at {} lines, it's no longer linear in the size of the script.",
+			sizes[sizes.len() - 1],
+			sizes[0]
+		);
+	}
+}
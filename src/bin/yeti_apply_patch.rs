@@ -0,0 +1,33 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_apply_patch_command;
+use yeti::{flag_value, main_preamble};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti patcher. Usage: yeti_apply_patch <original sn.bin> <patch.bps> --output=<file>.
+Applies a patch made with yeti_make_patch to the game's original scenario file. The patch knows
+which file it was made for, and nothing is written if the scenario file doesn't match.
+Options:
+
+--output=<file> 				Where to write the patched scenario file.
+"#
+		);
+		std::process::exit(0);
+	}
+
+	let (files, _) = main_preamble("");
+	let [original, patch] = files.as_slice() else {
+		log::error!("Expected the original scenario file and the patch.");
+		std::process::exit(1);
+	};
+	let Some(output) = flag_value("output").map(Utf8PathBuf::from) else {
+		log::error!("Where should the patched scenario go? Pass --output=<file>.");
+		std::process::exit(1);
+	};
+
+	if let Err(e) = do_apply_patch_command(original, patch, &output) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
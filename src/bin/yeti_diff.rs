@@ -0,0 +1,62 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_diff_command;
+use yeti::opcodescript::Quirks;
+use yeti::{flag_value, main_preamble, parse_quirks_arg};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti scenario diff. Usage: yeti_diff <old sn.bin or yaml directory> <new sn.bin or yaml directory>.
+Compares two scenarios script by script, for example two platform releases of the same game.
+Opcodes are compared by what they do rather than by their addresses, and text changes are shown
+old and new next to each other.
+Options:
+
+-q 								Sets quirks to use for both scenarios. The following quirks are available:
+									{}.
+--old-quirks=<quirks> 			Sets quirks to use for the old scenario only.
+--new-quirks=<quirks> 			Sets quirks to use for the new scenario only.
+--raw 							Neither scenario file is compressed.
+--old-raw 						The old scenario file isn't compressed.
+--new-raw 						The new scenario file isn't compressed.
+--output=<file> 				Write the differences to this file instead of printing them.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+"#,
+			Quirks::names().join(", ")
+		);
+		std::process::exit(0);
+	}
+
+	let args: Vec<String> = std::env::args().skip(1).collect();
+	// The file list from main_preamble would list the contents of yaml directories, so the two
+	// scenarios are taken from the arguments as given.
+	let paths: Vec<Utf8PathBuf> = args
+		.iter()
+		.enumerate()
+		.filter(|(idx, it)| !it.starts_with('-') && (*idx == 0 || args[idx - 1] != "-q"))
+		.map(|(_, it)| Utf8PathBuf::from(it))
+		.collect();
+	let raw = std::env::args().any(|it| it == "--raw");
+	let old_raw = raw || std::env::args().any(|it| it == "--old-raw");
+	let new_raw = raw || std::env::args().any(|it| it == "--new-raw");
+	let output = flag_value("output").map(Utf8PathBuf::from);
+
+	let (_, quirks) = main_preamble("");
+	let old_quirks = parse_quirks_arg(&flag_value("old-quirks").unwrap_or_else(|| quirks.clone()));
+	let new_quirks = parse_quirks_arg(&flag_value("new-quirks").unwrap_or(quirks));
+
+	let [old, new] = paths.as_slice() else {
+		log::error!("Expected two scenarios to compare.");
+		std::process::exit(1);
+	};
+	if let Err(e) = do_diff_command(
+		old,
+		(old_raw, old_quirks),
+		new,
+		(new_raw, new_quirks),
+		output.as_deref(),
+	) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
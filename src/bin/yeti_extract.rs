@@ -0,0 +1,59 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_extract_entry_command;
+use yeti::opcodescript::Quirks;
+use yeti::util::current_dir;
+use yeti::{flag_value, main_preamble, parse_quirks_arg};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti single script extractor. Usage: yeti_extract <sn.bin file> --entry=<number> [--yaml].
+Writes one script from a scenario file, without unpacking the rest.
+Options:
+
+-q 								Sets quirks to use. The following quirks are available to be used:
+									{}.
+--entry=<number> 				The entry to extract.
+--yaml 							Write the disassembled yaml script instead of the raw bytes.
+--output=<file> 				Where to write the script. Defaults to ./<entry>.opcodescript,
+									or ./<entry>.yaml with --yaml.
+--raw 							The scenario file isn't compressed.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+"#,
+			Quirks::names().join(", ")
+		);
+		std::process::exit(0);
+	}
+
+	let raw = std::env::args().any(|it| it == "--raw");
+	let yaml = std::env::args().any(|it| it == "--yaml");
+	let Some(entry) =
+		flag_value("entry").map(|it| it.parse::<usize>().expect("Expected an entry number!"))
+	else {
+		eprintln!("Which entry? Pass --entry=<number>.");
+		std::process::exit(1);
+	};
+	let (mut files, quirks) = main_preamble("");
+	if files.is_empty() {
+		files.extend(yeti::config::get().scenario.clone());
+	}
+	let quirks = parse_quirks_arg(&quirks);
+	let output = flag_value("output")
+		.map(Utf8PathBuf::from)
+		.unwrap_or_else(|| {
+			let extension = if yaml { "yaml" } else { "opcodescript" };
+			current_dir().join(format!("{entry:04}.{extension}"))
+		});
+
+	let Some(file) = files.first() else {
+		log::error!("No scenario file given.");
+		std::process::exit(1);
+	};
+	let result = std::fs::read(file)
+		.map_err(anyhow::Error::new)
+		.and_then(|data| do_extract_entry_command(data, raw, entry, yaml, &output, quirks));
+	if let Err(e) = result {
+		log::error!("{file}: {e:#}");
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,44 @@
+use yeti::commands::do_info_command;
+use yeti::opcodescript::Quirks;
+use yeti::{flag_value, main_preamble, parse_quirks_arg};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti scenario summary. Usage: yeti_info <sn.bin file> [--entry=<number>].
+Shows the compressed and decompressed size of a scenario file, how many entries it has, and any
+gaps or overlaps between them. With --entry, shows the details of that entry instead.
+Options:
+
+-q 								Sets quirks to use. The following quirks are available to be used:
+									{}.
+--entry=<number> 				Describe a single entry.
+--raw 							The scenario file isn't compressed.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+"#,
+			Quirks::names().join(", ")
+		);
+		std::process::exit(0);
+	}
+
+	let raw = std::env::args().any(|it| it == "--raw");
+	let entry =
+		flag_value("entry").map(|it| it.parse::<usize>().expect("Expected an entry number!"));
+	let (mut files, quirks) = main_preamble("");
+	if files.is_empty() {
+		files.extend(yeti::config::get().scenario.clone());
+	}
+	let quirks = parse_quirks_arg(&quirks);
+
+	let Some(file) = files.first() else {
+		log::error!("No scenario file given.");
+		std::process::exit(1);
+	};
+	let result = std::fs::read(file)
+		.map_err(anyhow::Error::new)
+		.and_then(|data| do_info_command(data, raw, entry, quirks));
+	if let Err(e) = result {
+		log::error!("{file}: {e:#}");
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,41 @@
+use yeti::commands::do_ls_command;
+use yeti::opcodescript::Quirks;
+use yeti::{main_preamble, parse_quirks_arg};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti scenario lister. Usage: yeti_ls <sn.bin file> [-q comma,separated,quirks].
+Lists every entry in a scenario file, with its offset, size, first and last opcodes, and any
+gaps or overlaps between entries.
+Options:
+
+-q 								Sets quirks to use. The following quirks are available to be used:
+									{}.
+--raw 							The scenario file isn't compressed.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+"#,
+			Quirks::names().join(", ")
+		);
+		std::process::exit(0);
+	}
+
+	let raw = std::env::args().any(|it| it == "--raw");
+	let (mut files, quirks) = main_preamble("");
+	if files.is_empty() {
+		files.extend(yeti::config::get().scenario.clone());
+	}
+	let quirks = parse_quirks_arg(&quirks);
+
+	let Some(file) = files.first() else {
+		log::error!("No scenario file given.");
+		std::process::exit(1);
+	};
+	let result = std::fs::read(file)
+		.map_err(anyhow::Error::new)
+		.and_then(|data| do_ls_command(data, raw, quirks));
+	if let Err(e) = result {
+		log::error!("{file}: {e:#}");
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,37 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_make_patch_command;
+use yeti::util::current_dir;
+use yeti::{flag_value, main_preamble};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti patch maker. Usage: yeti_make_patch <original sn.bin> <rebuilt sn.bin> [--output=<file>].
+Writes a BPS patch that turns the original scenario file into the rebuilt one, so the translation
+can be shared without sharing the game's files. Apply it with yeti_apply_patch, or any BPS patcher.
+Options:
+
+--output=<file> 				Where to write the patch. Defaults to ./sn.bin.bps.
+--decompressed 					Make the patch between the decompressed scenario files. The patch is
+									much smaller, but can only be applied with yeti_apply_patch.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+"#
+		);
+		std::process::exit(0);
+	}
+
+	let decompressed = std::env::args().any(|it| it == "--decompressed");
+	let (files, _) = main_preamble("");
+	let [original, rebuilt] = files.as_slice() else {
+		log::error!("Expected the original and the rebuilt scenario files.");
+		std::process::exit(1);
+	};
+	let output = flag_value("output")
+		.map(Utf8PathBuf::from)
+		.unwrap_or_else(|| current_dir().join("sn.bin.bps"));
+
+	if let Err(e) = do_make_patch_command(original, rebuilt, &output, decompressed) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
@@ -1,35 +1,52 @@
 use camino::Utf8PathBuf;
-use yeti::{main_preamble};
-use yeti::commands::{do_archive_command};
-use yeti::opcodescript::Quirks;
-use yeti::util::{current_dir};
+use yeti::commands::{do_archive_command, do_assemble_command, PackOutput};
+use yeti::{flag_value, main_preamble, pack_options, pack_output, PackOptions};
 
 fn main() {
 	if std::env::args().any(|it| it == "-h" || it == "--help") {
-		println!(r#"Yeti scenario repacker. Usage: yeti_pack <sn.bin.script directory>.
+		println!(r#"Yeti scenario repacker. Usage: yeti_pack <sn.bin.script directory> [--clean].
 This tool will put the output sn.bin file in the direcory where it is run from. 
+Paths left out on the command line are read from yeti.toml, if there is one.
+Options:
+
+--clean 						Ignore the build cache and re-serialize every script.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+--yaml-dir=<dir> 				The directory containing directory.yaml and the yaml scripts.
+--text-dir=<dir> 				The directory containing the translation documents.
+--output=<file> 				Where to write the new sn.bin.
+--no-compress 					Don't compress the output.
+--loose-scripts 				Write each script to its own .opcodescript file in the output directory,
+									along with directory.yaml, instead of packing them into sn.bin.
+									The output directory defaults to ./sn.bin.opcodescript.
+--from-opcodescript=<dir> 		Pack the .opcodescript files in this directory into sn.bin, in the order
+									given by the directory.yaml next to them, instead of the yaml scripts.
 "#);
 		std::process::exit(0);
 	}
 
-	let (files, _) = main_preamble(&"txt");
+	let clean = std::env::args().any(|it| it == "--clean");
 
-	let files = if files.is_empty() {
-		let args = std::env::args().collect::<Vec<_>>();
-		args.iter().skip(1).map(|it| Utf8PathBuf::from(it).join("nonexistant")).collect::<Vec<_>>()
-	} else {
-		files
-	};
+	if let Some(script_dir) = flag_value("from-opcodescript").map(Utf8PathBuf::from) {
+		main_preamble("");
+		let (output, PackOutput::Scenario { compress }) = pack_output() else {
+			log::error!("--loose-scripts can't be used with --from-opcodescript.");
+			std::process::exit(1);
+		};
+		if let Err(e) = do_assemble_command(&script_dir, &output, compress) {
+			log::error!("{e:#}");
+			std::process::exit(1);
+		}
+		return;
+	}
 
-	let current_folder = files
-		.first()
-		.expect("Expected the folder to contain files!")
-		.parent()
-		.unwrap();
+	let (files, _) = main_preamble("txt");
 
-	let yaml_folder = current_folder
-		.parent().unwrap()
-		.join(current_folder.file_name().expect("Expected folder to have a file name!").replace(".script", ".yaml"));
+	let PackOptions {
+		yaml_dir,
+		text_dir,
+		output,
+		output_mode,
+	} = pack_options(&files);
 
-	do_archive_command(&yaml_folder, current_folder, &current_dir().join("sn.bin"), true, true)
+	do_archive_command(&yaml_dir, &text_dir, &output, output_mode, true, clean)
 }
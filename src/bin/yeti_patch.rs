@@ -0,0 +1,62 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_patch_command;
+use yeti::{flag_value, main_preamble};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti scenario patcher. Usage: yeti_patch <sn.bin file> <yaml scripts...> --output=<file>.
+Replaces a few scripts in an existing scenario file, without repacking the others. Each script
+replaces the entry it's named after, so 0045.yaml replaces entry 45.
+Paths left out on the command line are read from yeti.toml, if there is one.
+Options:
+
+--output=<file> 				Where to write the patched scenario file.
+--text-dir=<dir> 				The directory containing the translation documents.
+--untranslated 					Don't apply translation documents to the scripts.
+--raw 							The scenario file isn't compressed, and neither is the output.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+"#
+		);
+		std::process::exit(0);
+	}
+
+	let raw = std::env::args().any(|it| it == "--raw");
+	let untranslated = std::env::args().any(|it| it == "--untranslated");
+
+	let (files, _) = main_preamble("");
+	let config = yeti::config::get();
+
+	let (scripts, others): (Vec<_>, Vec<_>) = files
+		.into_iter()
+		.partition(|it| it.extension() == Some("yaml"));
+	// Directories of scripts can be given too, but only numbered scripts are entries.
+	let scripts: Vec<_> = scripts
+		.into_iter()
+		.filter(|it| it.file_stem().is_some_and(|it| it.parse::<usize>().is_ok()))
+		.collect();
+	let Some(scenario) = others
+		.into_iter()
+		.next()
+		.or_else(|| config.scenario.clone())
+	else {
+		log::error!("No scenario file given.");
+		std::process::exit(1);
+	};
+	let Some(output) = flag_value("output")
+		.map(Utf8PathBuf::from)
+		.or_else(|| config.output.clone())
+	else {
+		log::error!("Where should the patched scenario go? Pass --output=<file>.");
+		std::process::exit(1);
+	};
+	let text_dir = flag_value("text-dir")
+		.map(Utf8PathBuf::from)
+		.or_else(|| config.text_dir.clone())
+		.filter(|_| !untranslated);
+
+	if let Err(e) = do_patch_command(&scenario, &scripts, text_dir.as_deref(), &output, !raw) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
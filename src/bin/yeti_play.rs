@@ -0,0 +1,41 @@
+use yeti::commands::do_play_command;
+use yeti::{flag_value, main_preamble, pack_options, PackOptions};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti script player. Usage: yeti_play <sn.bin.script directory> [--start=<script>].
+Plays through the scenario on the terminal, using the translation where there is one.
+Choices are picked by typing their number.
+Paths left out on the command line are read from yeti.toml, if there is one.
+Options:
+
+--start=<script> 				The index of the script to start from. Defaults to 0.
+--tips=<level> 					Only show custom tips up to this level. Shows all tips by default.
+--untranslated 					Show the original text instead of the translation.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+--yaml-dir=<dir> 				The directory containing directory.yaml and the yaml scripts.
+--text-dir=<dir> 				The directory containing the translation documents.
+"#
+		);
+		std::process::exit(0);
+	}
+
+	let start = flag_value("start").map_or(0, |it| it.parse().expect("Expected a script index!"));
+	let tip_level = flag_value("tips").map_or(u8::MAX, |it| {
+		it.parse().expect("Expected a tip level between 0 and 255!")
+	});
+	let untranslated = std::env::args().any(|it| it == "--untranslated");
+
+	let (files, _) = main_preamble("txt");
+
+	let PackOptions {
+		yaml_dir, text_dir, ..
+	} = pack_options(&files);
+
+	let text_dir = (!untranslated).then_some(text_dir.as_path());
+	if let Err(e) = do_play_command(&yaml_dir, text_dir, start, tip_level) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,66 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_port_command;
+use yeti::{flag_value, main_preamble};
+
+/// The yaml directory that goes with a script text directory, `sn.bin.script` -> `sn.bin.yaml`.
+fn yaml_dir(text_dir: &Utf8PathBuf) -> Option<Utf8PathBuf> {
+	let name = text_dir.file_name()?.replace(".script", ".yaml");
+	Some(text_dir.parent()?.join(name))
+}
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti translation porter. Usage: yeti_port <translated sn.bin.script directory> <target sn.bin.script directory> [--report=<file>].
+Carries a translation over to another release of the same game. Both releases must be unpacked
+already, each with its own quirks. Lines are matched by their original text within each script,
+and the translations and notes are written into the target's yaml scripts and translation
+documents. Translated lines that couldn't be placed are listed in the report.
+Options:
+
+--report=<file> 				Where to list the lines that couldn't be placed. Defaults to ./unplaced.csv.
+--source-yaml-dir=<dir> 		The yaml directory of the translated release, if it isn't next to its text directory.
+--target-yaml-dir=<dir> 		The yaml directory of the target release, if it isn't next to its text directory.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+"#
+		);
+		std::process::exit(0);
+	}
+
+	// The file list from main_preamble would list the contents of the directories, so they're
+	// taken from the arguments as given.
+	let dirs: Vec<Utf8PathBuf> = std::env::args()
+		.skip(1)
+		.filter(|it| !it.starts_with('-'))
+		.map(Utf8PathBuf::from)
+		.collect();
+	let report = flag_value("report")
+		.map(Utf8PathBuf::from)
+		.unwrap_or_else(|| yeti::util::current_dir().join("unplaced.csv"));
+
+	main_preamble("");
+
+	let [source_text_dir, target_text_dir] = dirs.as_slice() else {
+		log::error!("Expected the translated and the target script text directories.");
+		std::process::exit(1);
+	};
+	let source_dir = flag_value("source-yaml-dir")
+		.map(Utf8PathBuf::from)
+		.or_else(|| yaml_dir(source_text_dir))
+		.expect("Expected folder to have a file name!");
+	let target_dir = flag_value("target-yaml-dir")
+		.map(Utf8PathBuf::from)
+		.or_else(|| yaml_dir(target_text_dir))
+		.expect("Expected folder to have a file name!");
+
+	if let Err(e) = do_port_command(
+		&source_dir,
+		source_text_dir,
+		&target_dir,
+		target_text_dir,
+		&report,
+	) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,42 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_reach_command;
+use yeti::util::current_dir;
+use yeti::{flag_value, main_preamble, pack_options, PackOptions};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti reachability check. Usage: yeti_reach <sn.bin.script directory> [--output=<file>].
+Lists every opcode that no path through the scenario reaches as CSV, and warns about any text
+that could be reached when the scenario was unpacked, but can't be any more.
+Every script is treated as a possible starting point, unless --entry is given.
+Paths left out on the command line are read from yeti.toml, if there is one.
+Options:
+
+--output=<file> 				Where to write the list. Defaults to ./unreachable.csv.
+--entry=<scripts> 				Comma separated list of the scripts the game can start from.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+--yaml-dir=<dir> 				The directory containing directory.yaml and the yaml scripts.
+"#
+		);
+		std::process::exit(0);
+	}
+
+	let entries = flag_value("entry").map(|it| {
+		it.split(',')
+			.map(|it| it.trim().parse().expect("Expected a script index!"))
+			.collect::<Vec<usize>>()
+	});
+
+	let (files, _) = main_preamble("txt");
+
+	let PackOptions { yaml_dir, .. } = pack_options(&files);
+	let output = flag_value("output")
+		.map(Utf8PathBuf::from)
+		.unwrap_or_else(|| current_dir().join("unreachable.csv"));
+
+	if let Err(e) = do_reach_command(&yaml_dir, entries.as_deref(), &output) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,55 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_route_command;
+use yeti::{flag_value, main_preamble, pack_options, PackOptions};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti route runner. Usage: yeti_route <sn.bin.script directory> --choices=<file> [--golden=<file>].
+Plays through a route without interaction and prints what's shown on screen.
+The choice file lists the option to take at each choice, counting from 0, e.g. `0,1,1,0`.
+With --golden, the transcript is compared against that file instead, and the tool exits with an
+error if they differ. The golden file is created if it doesn't exist yet.
+Paths left out on the command line are read from yeti.toml, if there is one.
+Options:
+
+--choices=<file> 				The choice sequence to follow.
+--start=<script> 				The index of the script to start from. Defaults to 0.
+--golden=<file> 				Compare the transcript against this file.
+--bless 						Overwrite the golden file with the new transcript.
+--untranslated 					Use the original text instead of the translation.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+--yaml-dir=<dir> 				The directory containing directory.yaml and the yaml scripts.
+--text-dir=<dir> 				The directory containing the translation documents.
+"#
+		);
+		std::process::exit(0);
+	}
+
+	let choices = flag_value("choices")
+		.map(Utf8PathBuf::from)
+		.expect("Expected a choice sequence file (--choices=<file>)!");
+	let golden = flag_value("golden").map(Utf8PathBuf::from);
+	let start = flag_value("start").map_or(0, |it| it.parse().expect("Expected a script index!"));
+	let bless = std::env::args().any(|it| it == "--bless");
+	let untranslated = std::env::args().any(|it| it == "--untranslated");
+
+	let (files, _) = main_preamble("txt");
+
+	let PackOptions {
+		yaml_dir, text_dir, ..
+	} = pack_options(&files);
+
+	let text_dir = (!untranslated).then_some(text_dir.as_path());
+	if let Err(e) = do_route_command(
+		&yaml_dir,
+		text_dir,
+		start,
+		&choices,
+		golden.as_deref(),
+		bless,
+	) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
@@ -1,5 +1,5 @@
 use camino::{Utf8Path, Utf8PathBuf};
-use yeti::{main_preamble, parse_quirks_arg};
+use yeti::{flag_value, main_preamble, parse_quirks_arg};
 use yeti::opcodescript::Quirks;
 use yeti::util::{current_dir, safe_create_dir};
 
@@ -14,29 +14,51 @@ Options:
 									{}.
 									Be careful when mixing these, it can cause problems.
 									Example: yeti_unpack.exe sn.bin -q psp,ccfc
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+--yaml-dir=<dir> 				Write the yaml scripts here instead.
+--text-dir=<dir> 				Write the translation documents here instead.
+--recover 						Keep undecodable bytes as OP_RawBytes and carry on disassembling
+									after them, instead of stopping at the first unknown opcode.
+
+Anything left out on the command line is read from yeti.toml, if there is one.
 "#,
 						 Quirks::names().join(", "));
 		std::process::exit(0);
 	}
 	
-	let (files, quirks) = main_preamble(&"sn.bin");
+	let (mut files, quirks) = main_preamble(&"sn.bin");
+	let config = yeti::config::get();
+	if files.is_empty() {
+		files.extend(config.scenario.clone());
+	}
+	let yaml_dir = flag_value("yaml-dir").map(Utf8PathBuf::from).or_else(|| config.yaml_dir.clone());
+	let text_dir = flag_value("text-dir").map(Utf8PathBuf::from).or_else(|| config.text_dir.clone());
+
 	let top_out_path = &current_dir().join("extracted_scenario");
 	safe_create_dir(&top_out_path).unwrap();
 
 	let quirks = parse_quirks_arg(&quirks);
 
+	let mut failed = false;
 	for i in files {
 		let dirent = i;
 		let out_folder_base_name = &top_out_path.join(dirent.file_name().unwrap());
-		let out_yaml_folder = &out_folder_base_name.with_extension("bin.yaml");
-		let out_script_folder = &out_folder_base_name.with_extension("bin.script");
+		let out_yaml_folder = &yaml_dir.clone().unwrap_or_else(|| out_folder_base_name.with_extension("bin.yaml"));
+		let out_script_folder = &text_dir.clone().unwrap_or_else(|| out_folder_base_name.with_extension("bin.script"));
 
 		safe_create_dir(&out_folder_base_name).unwrap();
-		safe_create_dir(&out_yaml_folder).unwrap();
-		safe_create_dir(&out_script_folder).unwrap();
+		std::fs::create_dir_all(&out_yaml_folder).unwrap();
+		std::fs::create_dir_all(&out_script_folder).unwrap();
 
 		let file_contents = std::fs::read(&dirent).unwrap();
 
-		yeti::commands::do_unpack_command(file_contents, &out_yaml_folder, &out_script_folder, quirks);
+		if let Err(e) = yeti::commands::do_unpack_command(file_contents, &out_yaml_folder, &out_script_folder, quirks) {
+			log::error!("{dirent}: {e}");
+			failed = true;
+		}
+	}
+
+	if failed {
+		std::process::exit(1);
 	}
 }
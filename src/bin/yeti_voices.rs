@@ -0,0 +1,41 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_voice_command;
+use yeti::util::current_dir;
+use yeti::{flag_value, main_preamble, pack_options, PackOptions};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti voice index. Usage: yeti_voices <sn.bin.script directory> [--output=<file>].
+Writes a CSV table of every voice clip the scenario plays, along with the speaker, original text
+and translation of the line it belongs to, and where it is in the scenario.
+Paths left out on the command line are read from yeti.toml, if there is one.
+Options:
+
+--output=<file> 				Where to write the table. Defaults to ./voices.csv.
+--untranslated 					Leave the translation column empty.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+--yaml-dir=<dir> 				The directory containing directory.yaml and the yaml scripts.
+--text-dir=<dir> 				The directory containing the translation documents.
+"#
+		);
+		std::process::exit(0);
+	}
+
+	let untranslated = std::env::args().any(|it| it == "--untranslated");
+
+	let (files, _) = main_preamble("txt");
+
+	let PackOptions {
+		yaml_dir, text_dir, ..
+	} = pack_options(&files);
+	let output = flag_value("output")
+		.map(Utf8PathBuf::from)
+		.unwrap_or_else(|| current_dir().join("voices.csv"));
+
+	let text_dir = (!untranslated).then_some(text_dir.as_path());
+	if let Err(e) = do_voice_command(&yaml_dir, text_dir, &output) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,24 @@
+use yeti::commands::do_watch_command;
+use yeti::{main_preamble, pack_options, PackOptions};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(r#"Yeti scenario watcher. Usage: yeti_watch <sn.bin.script directory>.
+This tool repacks sn.bin into the directory where it is run from every time a script
+or translation document changes. Press Ctrl+C to stop watching.
+Takes the same options as yeti_pack, apart from --clean.
+"#);
+		std::process::exit(0);
+	}
+
+	let (files, _) = main_preamble("txt");
+
+	let PackOptions {
+		yaml_dir,
+		text_dir,
+		output,
+		output_mode,
+	} = pack_options(&files);
+
+	do_watch_command(&yaml_dir, &text_dir, &output, output_mode, true)
+}
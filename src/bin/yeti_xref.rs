@@ -0,0 +1,36 @@
+use camino::Utf8PathBuf;
+use yeti::commands::do_xref_command;
+use yeti::util::current_dir;
+use yeti::{flag_value, main_preamble, pack_options, PackOptions};
+
+fn main() {
+	if std::env::args().any(|it| it == "-h" || it == "--help") {
+		println!(
+			r#"Yeti variable cross-reference. Usage: yeti_xref <sn.bin.script directory> [--output=<file>].
+Lists every script variable read or written by the scenario, with the script, address and opcode
+of each use, as both JSON and CSV.
+Variables can be given names with a name map file, set as variable_names in yeti.toml. The names
+are also added as comments to the yaml scripts when unpacking.
+Paths left out on the command line are read from yeti.toml, if there is one.
+Options:
+
+--output=<file> 				Where to write the report, without extension. Defaults to ./variables.
+--config=<file> 				Use this project configuration instead of ./yeti.toml.
+--yaml-dir=<dir> 				The directory containing directory.yaml and the yaml scripts.
+"#
+		);
+		std::process::exit(0);
+	}
+
+	let (files, _) = main_preamble("txt");
+
+	let PackOptions { yaml_dir, .. } = pack_options(&files);
+	let output = flag_value("output")
+		.map(Utf8PathBuf::from)
+		.unwrap_or_else(|| current_dir().join("variables"));
+
+	if let Err(e) = do_xref_command(&yaml_dir, &output) {
+		log::error!("{e:#}");
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,360 @@
+//! Creating and applying patches in the BPS format, so translations can be shared without
+//! sharing the game's own files.
+//!
+//! A BPS patch describes the target file as a series of copies from the source file, copies from
+//! earlier in the target, and new bytes, followed by CRC32s of the source, the target and the
+//! patch itself. The CRCs let us refuse to patch the wrong base file.
+
+use anyhow::{anyhow, Result};
+
+const MAGIC: &[u8] = b"BPS1";
+
+/// Matches shorter than this are written as new bytes, since a copy would take up about as much
+/// space.
+const MIN_MATCH: usize = 8;
+
+/// log2 of the number of slots in the tables used to find matches.
+const TABLE_BITS: u32 = 22;
+
+const SOURCE_READ: u64 = 0;
+const TARGET_READ: u64 = 1;
+const SOURCE_COPY: u64 = 2;
+const TARGET_COPY: u64 = 3;
+
+/// The standard (IEEE) CRC32, as used by BPS.
+pub fn crc32(data: &[u8]) -> u32 {
+	static TABLE: once_cell::sync::Lazy<[u32; 256]> = once_cell::sync::Lazy::new(|| {
+		let mut table = [0u32; 256];
+		for (idx, entry) in table.iter_mut().enumerate() {
+			let mut crc = idx as u32;
+			for _ in 0..8 {
+				crc = if crc & 1 == 1 {
+					(crc >> 1) ^ 0xEDB8_8320
+				} else {
+					crc >> 1
+				};
+			}
+			*entry = crc;
+		}
+		table
+	});
+
+	!data.iter().fold(!0u32, |crc, &byte| {
+		TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+	})
+}
+
+fn write_number(output: &mut Vec<u8>, mut number: u64) {
+	loop {
+		let bits = (number & 0x7F) as u8;
+		number >>= 7;
+		if number == 0 {
+			output.push(0x80 | bits);
+			break;
+		}
+		output.push(bits);
+		number -= 1;
+	}
+}
+
+fn write_offset(output: &mut Vec<u8>, from: usize, to: usize) {
+	let (distance, negative) = if to >= from {
+		(to - from, 0)
+	} else {
+		(from - to, 1)
+	};
+	write_number(output, ((distance as u64) << 1) | negative);
+}
+
+fn slot(data: &[u8]) -> usize {
+	let key = u64::from_le_bytes(data[..8].try_into().unwrap());
+	(key.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> (64 - TABLE_BITS)) as usize
+}
+
+fn common_length(a: &[u8], b: &[u8]) -> usize {
+	a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// Creates a patch that turns `source` into `target`. `metadata` is stored in the patch as is.
+pub fn create(source: &[u8], target: &[u8], metadata: &str) -> Vec<u8> {
+	let mut patch = MAGIC.to_vec();
+	write_number(&mut patch, source.len() as u64);
+	write_number(&mut patch, target.len() as u64);
+	write_number(&mut patch, metadata.len() as u64);
+	patch.extend(metadata.as_bytes());
+
+	// Each slot holds the last position (plus one) of a run of bytes with that hash.
+	let mut source_table = vec![0u32; 1 << TABLE_BITS];
+	for pos in 0..source.len().saturating_sub(MIN_MATCH - 1) {
+		source_table[slot(&source[pos..])] = pos as u32 + 1;
+	}
+	let mut target_table = vec![0u32; 1 << TABLE_BITS];
+
+	let mut literal_start = 0;
+	let mut source_relative = 0;
+	let mut target_relative = 0;
+	let mut pos = 0;
+
+	while pos < target.len() {
+		let rest = &target[pos..];
+		let (mut length, mut command, mut from) = (0, SOURCE_READ, pos);
+		if pos < source.len() {
+			length = common_length(&source[pos..], rest);
+		}
+
+		if rest.len() >= MIN_MATCH {
+			let slot = slot(rest);
+			if let Some(candidate) = source_table[slot].checked_sub(1) {
+				let candidate = candidate as usize;
+				let candidate_length = common_length(&source[candidate..], rest);
+				if candidate_length > length {
+					(length, command, from) = (candidate_length, SOURCE_COPY, candidate);
+				}
+			}
+			if let Some(candidate) = target_table[slot].checked_sub(1) {
+				let candidate = candidate as usize;
+				let candidate_length = common_length(&target[candidate..], rest);
+				if candidate_length > length {
+					(length, command, from) = (candidate_length, TARGET_COPY, candidate);
+				}
+			}
+		}
+
+		if length < MIN_MATCH {
+			if rest.len() >= MIN_MATCH {
+				target_table[slot(rest)] = pos as u32 + 1;
+			}
+			pos += 1;
+			continue;
+		}
+
+		if literal_start < pos {
+			write_number(
+				&mut patch,
+				((pos - literal_start - 1) as u64) << 2 | TARGET_READ,
+			);
+			patch.extend(&target[literal_start..pos]);
+		}
+
+		write_number(&mut patch, ((length - 1) as u64) << 2 | command);
+		match command {
+			SOURCE_COPY => {
+				write_offset(&mut patch, source_relative, from);
+				source_relative = from + length;
+			}
+			TARGET_COPY => {
+				write_offset(&mut patch, target_relative, from);
+				target_relative = from + length;
+			}
+			_ => {}
+		}
+
+		for skipped in pos..(pos + length).min(target.len().saturating_sub(MIN_MATCH - 1)) {
+			target_table[slot(&target[skipped..])] = skipped as u32 + 1;
+		}
+		pos += length;
+		literal_start = pos;
+	}
+
+	if literal_start < target.len() {
+		write_number(
+			&mut patch,
+			((target.len() - literal_start - 1) as u64) << 2 | TARGET_READ,
+		);
+		patch.extend(&target[literal_start..]);
+	}
+
+	patch.extend(crc32(source).to_le_bytes());
+	patch.extend(crc32(target).to_le_bytes());
+	let patch_crc = crc32(&patch);
+	patch.extend(patch_crc.to_le_bytes());
+
+	patch
+}
+
+struct Reader<'a> {
+	data: &'a [u8],
+	pos: usize,
+}
+
+impl Reader<'_> {
+	fn byte(&mut self) -> Result<u8> {
+		let byte = *self
+			.data
+			.get(self.pos)
+			.ok_or_else(|| anyhow!("The patch ends unexpectedly."))?;
+		self.pos += 1;
+		Ok(byte)
+	}
+
+	fn bytes(&mut self, length: usize) -> Result<&[u8]> {
+		let bytes = self
+			.data
+			.get(self.pos..self.pos + length)
+			.ok_or_else(|| anyhow!("The patch ends unexpectedly."))?;
+		self.pos += length;
+		Ok(bytes)
+	}
+
+	fn number(&mut self) -> Result<u64> {
+		let mut number = 0u64;
+		let mut shift = 1u64;
+		loop {
+			let byte = self.byte()?;
+			number = (byte as u64 & 0x7F)
+				.checked_mul(shift)
+				.and_then(|it| it.checked_add(number))
+				.ok_or_else(|| anyhow!("The patch contains an invalid number."))?;
+			if byte & 0x80 != 0 {
+				return Ok(number);
+			}
+			shift = shift
+				.checked_shl(7)
+				.filter(|it| *it != 0)
+				.ok_or_else(|| anyhow!("The patch contains an invalid number."))?;
+			number += shift;
+		}
+	}
+
+	fn offset(&mut self, relative: usize) -> Result<usize> {
+		let number = self.number()?;
+		let distance = (number >> 1) as usize;
+		if number & 1 == 1 {
+			relative.checked_sub(distance)
+		} else {
+			relative.checked_add(distance)
+		}
+		.ok_or_else(|| anyhow!("The patch copies from outside the file."))
+	}
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+	u32::from_le_bytes(data[..4].try_into().unwrap())
+}
+
+/// Reads the metadata stored in a patch, without applying it.
+pub fn metadata(patch: &[u8]) -> Result<String> {
+	if !patch.starts_with(MAGIC) {
+		return Err(anyhow!("This isn't a BPS patch."));
+	}
+
+	let mut reader = Reader {
+		data: patch,
+		pos: MAGIC.len(),
+	};
+	reader.number()?;
+	reader.number()?;
+	let metadata_size = reader.number()? as usize;
+	Ok(String::from_utf8_lossy(reader.bytes(metadata_size)?).into_owned())
+}
+
+/// Applies a patch to `source`, returning the patched file and the patch's metadata.
+///
+/// Fails without patching anything if the patch is damaged or was made for a different file.
+pub fn apply(source: &[u8], patch: &[u8]) -> Result<(Vec<u8>, String)> {
+	if patch.len() < MAGIC.len() + 12 || !patch.starts_with(MAGIC) {
+		return Err(anyhow!("This isn't a BPS patch."));
+	}
+	let footer = &patch[patch.len() - 12..];
+	if crc32(&patch[..patch.len() - 4]) != read_u32(&footer[8..]) {
+		return Err(anyhow!("The patch is damaged, its checksum doesn't match."));
+	}
+
+	let mut reader = Reader {
+		data: &patch[..patch.len() - 12],
+		pos: MAGIC.len(),
+	};
+	let source_size = reader.number()? as usize;
+	let target_size = reader.number()? as usize;
+	let metadata_size = reader.number()? as usize;
+	let metadata = String::from_utf8_lossy(reader.bytes(metadata_size)?).into_owned();
+
+	if source.len() != source_size || crc32(source) != read_u32(footer) {
+		return Err(anyhow!(
+			"This patch is for a different file (expected 0x{source_size:X} bytes with CRC32 {:08X}, got 0x{:X} bytes with CRC32 {:08X}).",
+			read_u32(footer),
+			source.len(),
+			crc32(source)
+		));
+	}
+
+	let mut output = Vec::with_capacity(target_size);
+	let mut source_relative = 0;
+	let mut target_relative = 0;
+	while reader.pos < reader.data.len() {
+		let action = reader.number()?;
+		let length = (action >> 2) as usize + 1;
+		if output.len() + length > target_size {
+			return Err(anyhow!("The patch writes past the end of the file."));
+		}
+
+		match action & 3 {
+			SOURCE_READ => {
+				let start = output.len();
+				let bytes = source
+					.get(start..start + length)
+					.ok_or_else(|| anyhow!("The patch copies from outside the file."))?;
+				output.extend(bytes);
+			}
+			TARGET_READ => output.extend(reader.bytes(length)?),
+			SOURCE_COPY => {
+				source_relative = reader.offset(source_relative)?;
+				let bytes = source
+					.get(source_relative..source_relative + length)
+					.ok_or_else(|| anyhow!("The patch copies from outside the file."))?;
+				output.extend(bytes);
+				source_relative += length;
+			}
+			_ => {
+				target_relative = reader.offset(target_relative)?;
+				if target_relative >= output.len() {
+					return Err(anyhow!("The patch copies from outside the file."));
+				}
+				// The copy can overlap what it's writing, so it has to go byte by byte.
+				for _ in 0..length {
+					output.push(output[target_relative]);
+					target_relative += 1;
+				}
+			}
+		}
+	}
+
+	if output.len() != target_size || crc32(&output) != read_u32(&footer[4..]) {
+		return Err(anyhow!(
+			"Patching didn't produce the expected file, the patch may be damaged."
+		));
+	}
+
+	Ok((output, metadata))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{apply, crc32, create};
+
+	#[test]
+	fn test_bps_round_trip() {
+		assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+		let source: Vec<u8> = (0..4000u32).flat_map(|it| (it * 7).to_le_bytes()).collect();
+		let mut target = source.clone();
+		target.splice(100..120, b"a translated line of text".iter().copied());
+		target.extend_from_within(500..1500);
+		target.extend([0x41; 300]);
+
+		let patch = create(&source, &target, "compression=lz77");
+		assert!(patch.len() < 500);
+		let (output, metadata) = apply(&source, &patch).unwrap();
+		assert_eq!(output, target);
+		assert_eq!(metadata, "compression=lz77");
+		assert_eq!(super::metadata(&patch).unwrap(), metadata);
+
+		assert!(apply(&target, &patch).is_err());
+		let mut damaged = patch.clone();
+		damaged[10] ^= 1;
+		assert!(apply(&source, &damaged).is_err());
+
+		let (output, _) = apply(b"", &create(b"", b"new", "")).unwrap();
+		assert_eq!(output, b"new");
+	}
+}
@@ -1,110 +1,290 @@
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
+use rayon::prelude::{
+	IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
 
-use crate::scenario_pack::parse_script;
-use crate::util::{escape_str, unescape_str};
+use crate::pack_cache::{config_key, content_hash, PackCache};
+use crate::scenario_pack::{parse_script, report_parse_outcomes, ParseOutcome};
+use crate::util::{escape_str, unescape_str, write_atomic};
+use crate::voice::text_of;
 use crate::{
 	lz77,
 	opcodescript::{
-		Choice, ChoiceOpcode, Opcode, Quirks, Script, String47Opcode, StringOpcode, StringOpcode2,
+		BasicOpcode2, BasicOpcode4, Choice, ChoiceOpcode, InsertOpcode, Op44Opcode, Opcode,
+		OpcodeIter, OpcodeView, Quirks, Script, String47Opcode, StringOpcode, StringOpcode2,
+		TipOpcode,
 	},
-	scenario_pack::{parse_scenario, DirEntry},
-	util::fix_line,
+	scenario_pack::{entry_spacing, parse_scenario, DirEntry, Spacing},
+	util::{fix_line, fix_string},
 };
+use anyhow::Context;
 use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
 use once_cell::sync::Lazy;
-use std::{collections::HashMap, io::Read};
+use std::{
+	collections::{HashMap, HashSet},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// What packing a scenario produces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackOutput {
+	/// A scenario file, lz77 compressed unless `compress` is false.
+	Scenario { compress: bool },
+	/// A directory with the serialized scripts as `.opcodescript` files, next to a copy of
+	/// `directory.yaml`. [`do_assemble_command`] packs such a directory into a scenario file.
+	LooseScripts,
+}
 
 pub fn do_archive_command(
 	top_dir: &Path,
 	text_script_dir: &Path,
 	outfile: &Path,
-	compress: bool,
+	output: PackOutput,
 	apply_text: bool,
+	clean: bool,
 ) {
-	let data = std::fs::read_to_string(&top_dir.join("directory.yaml")).unwrap();
-	let directory: Vec<DirEntry> = serde_yml::from_str(&data).unwrap();
+	archive_scenario(top_dir, text_script_dir, outfile, output, apply_text, clean).unwrap()
+}
 
-	let scripts: Vec<(String, Script)> = directory
+/// The pack pipeline behind [`do_archive_command`], reporting problems instead of panicking so
+/// that long-running callers such as [`do_watch_command`] can keep going.
+pub fn archive_scenario(
+	top_dir: &Path,
+	text_script_dir: &Path,
+	outfile: &Path,
+	output: PackOutput,
+	apply_text: bool,
+	clean: bool,
+) -> anyhow::Result<()> {
+	let directory_path = top_dir.join("directory.yaml");
+	let data = std::fs::read_to_string(&directory_path)
+		.with_context(|| format!("Could not read {directory_path}"))?;
+	let directory: Vec<DirEntry> =
+		serde_yml::from_str(&data).with_context(|| format!("Could not parse {directory_path}"))?;
+
+	let cache = PackCache::new(top_dir, clean);
+	let config_key = config_key(crate::config::get());
+	let extras: Vec<[u8; 8]> = directory.iter().map(|it| it.extra).collect();
+
+	let start = std::time::Instant::now();
+	let reused = AtomicUsize::new(0);
+	let scripts: Vec<(String, Vec<u8>)> = directory
 		.into_par_iter()
 		.map(|DirEntry { name, .. }| {
 			let path = top_dir.join(&name);
 			log::debug!("Opening {path}");
-			let mut script: Script = serde_yml::from_slice(&std::fs::read(&path).unwrap()).unwrap();
-			if apply_text {
-				let text_path = text_script_dir.join(name).with_extension("txt");
-				let text = std::fs::read_to_string(text_path).unwrap_or_default();
+			let yaml = std::fs::read(&path).with_context(|| format!("Could not read {path}"))?;
+			let text = if apply_text {
+				let text_path = text_script_dir.join(&name).with_extension("txt");
+				Some(std::fs::read_to_string(text_path).unwrap_or_default())
+			} else {
+				None
+			};
+
+			let key = content_hash(&yaml, text.as_deref(), config_key);
+			if let Some(serialized) = cache.get(&name, key) {
+				log::debug!("Reusing cached serialization of {path}.");
+				reused.fetch_add(1, Ordering::Relaxed);
+				return Ok((path.to_string(), serialized));
+			}
+
+			let mut script: Script =
+				serde_yml::from_slice(&yaml).with_context(|| format!("Could not parse {path}"))?;
+			if let Some(text) = text {
 				tl_reverse_transform_script(&mut script, &text);
 			}
-			(path.to_string(), script)
+			validate_script(&path, &script)?;
+			log::debug!("Serializing {path}.");
+			let serialized = script.binary_serialize();
+			cache.put(&name, key, &serialized);
+			Ok((path.to_string(), serialized))
 		})
-		.collect();
+		.collect::<anyhow::Result<_>>()?;
+
+	let serialized_in = start.elapsed();
+	log::info!(
+		"Serialized {} scripts in {:.2}s, {} of them reused from the cache.",
+		scripts.len(),
+		serialized_in.as_secs_f32(),
+		reused.into_inner()
+	);
+
+	let start = std::time::Instant::now();
+	match output {
+		PackOutput::Scenario { compress } => {
+			let serialized: Vec<&[u8]> = scripts.iter().map(|(_, it)| it.as_slice()).collect();
+			let packed = pack_scenario(&serialized, &extras, compress);
+			let packed_in = start.elapsed();
+
+			let start = std::time::Instant::now();
+			write_atomic(outfile, &packed).with_context(|| format!("Could not write {outfile}"))?;
+			log::info!(
+				"Packed 0x{:X} bytes in {:.2}s, written in {:.2}s.",
+				packed.len(),
+				packed_in.as_secs_f32(),
+				start.elapsed().as_secs_f32()
+			);
+		}
+		PackOutput::LooseScripts => {
+			std::fs::create_dir_all(outfile)
+				.with_context(|| format!("Could not create {outfile}"))?;
+			let directory_copy = outfile.join("directory.yaml");
+			write_atomic(&directory_copy, data.as_bytes())
+				.with_context(|| format!("Could not write {directory_copy}"))?;
+			for (path, script) in scripts {
+				let path = outfile
+					.join(Path::new(&path).file_name().unwrap_or_default())
+					.with_extension("opcodescript");
+				write_atomic(&path, &script).with_context(|| format!("Could not write {path}"))?;
+			}
+			log::info!("Written in {:.2}s.", start.elapsed().as_secs_f32());
+		}
+	}
 
-	let n_scripts = scripts.len();
+	Ok(())
+}
 
-	let (directory, scripts_concat, scripts) = recompile_scripts(scripts, n_scripts);
+/// Lays out a scenario file: a directory giving each script's offset, size and the matching
+/// trailing bytes from `extras`, followed by the scripts themselves.
+///
+/// Everything is written straight into one buffer sized up front, or into the compressor.
+fn pack_scenario<T: AsRef<[u8]>>(scripts: &[T], extras: &[[u8; 8]], compress: bool) -> Vec<u8> {
+	let directory_size = 16 * scripts.len();
+	let size = directory_size + scripts.iter().map(|it| it.as_ref().len()).sum::<usize>();
+
+	let mut directory = Vec::with_capacity(directory_size);
+	let mut script_start = directory_size;
+	for (script, extra) in scripts.iter().zip(extras) {
+		let script_len = script.as_ref().len();
+		directory.extend((script_start as u32).to_le_bytes());
+		directory.extend((script_len as u32).to_le_bytes());
+		directory.extend(extra);
+		script_start += script_len;
+	}
 
-	if outfile.to_string().ends_with('/') {
-		std::fs::create_dir_all(&outfile).unwrap();
-		for (path, script) in scripts {
-			let newpath = path
-				.rsplit_once('/')
-				.unwrap()
-				.1
-				.replace("yaml", "opcodescript");
-			let newpath = outfile.join(newpath);
-			std::fs::write(newpath, script).unwrap();
+	if compress {
+		let mut writer = lz77::Lz77Writer::new(size);
+		writer.write(&directory);
+		for script in scripts {
+			writer.write(script.as_ref());
 		}
+		writer.finish()
 	} else {
-		let result: Vec<u8> = directory
-			.chain(scripts_concat.as_slice())
-			.bytes()
-			.map(Result::unwrap)
-			.collect();
+		let mut output = directory;
+		output.reserve_exact(size - directory_size);
+		for script in scripts {
+			output.extend(script.as_ref());
+		}
+		output
+	}
+}
 
-		let compressed = if compress {
-			lz77::lz77_compress(&result)
-		} else {
-			result
-		};
+/// Packs a directory of serialized `.opcodescript` scripts into a scenario file, in the order
+/// given by the `directory.yaml` next to them. The scripts can come from
+/// [`PackOutput::LooseScripts`], from [`do_reencode_command`], or be patched by hand.
+pub fn do_assemble_command(
+	script_dir: &Path,
+	outfile: &Path,
+	compress: bool,
+) -> anyhow::Result<()> {
+	let directory_path = script_dir.join("directory.yaml");
+	let data = std::fs::read_to_string(&directory_path)
+		.with_context(|| format!("Could not read {directory_path}"))?;
+	let directory: Vec<DirEntry> =
+		serde_yml::from_str(&data).with_context(|| format!("Could not parse {directory_path}"))?;
+
+	let extras: Vec<[u8; 8]> = directory.iter().map(|it| it.extra).collect();
+	let scripts = directory
+		.into_iter()
+		.map(|DirEntry { name, .. }| {
+			let path = script_dir.join(&name).with_extension("opcodescript");
+			std::fs::read(&path).with_context(|| format!("Could not read {path}"))
+		})
+		.collect::<anyhow::Result<Vec<_>>>()?;
 
-		std::fs::write(outfile, compressed).unwrap();
+	log::info!("Packing {} scripts from {script_dir}.", scripts.len());
+	let packed = pack_scenario(&scripts, &extras, compress);
+	write_atomic(outfile, &packed).with_context(|| format!("Could not write {outfile}"))
+}
+
+/// Repacks the scenario every time a file under the yaml or text directory changes.
+///
+/// Changes are detected by polling modification times, so this works the same everywhere without
+/// needing a file notification service. Errors are logged and the watch keeps running.
+pub fn do_watch_command(
+	top_dir: &Path,
+	text_script_dir: &Path,
+	outfile: &Path,
+	output: PackOutput,
+	apply_text: bool,
+) -> ! {
+	let mut last_snapshot = None;
+
+	loop {
+		let snapshot = watch_snapshot(&[top_dir, text_script_dir]);
+		if last_snapshot.as_ref() != Some(&snapshot) {
+			if last_snapshot.is_some() {
+				log::info!("Change detected, repacking {outfile}.");
+			}
+			last_snapshot = Some(snapshot);
+
+			let start = std::time::Instant::now();
+			let result = std::panic::catch_unwind(|| {
+				archive_scenario(top_dir, text_script_dir, outfile, output, apply_text, false)
+			});
+			match result {
+				Ok(Ok(())) => log::info!(
+					"Wrote {outfile} in {:.2}s. Watching for changes...",
+					start.elapsed().as_secs_f32()
+				),
+				Ok(Err(e)) => log::error!("Could not pack {outfile}: {e:#}"),
+				Err(_) => log::error!("Packing {outfile} panicked, see the message above."),
+			}
+		}
+
+		std::thread::sleep(WATCH_POLL_INTERVAL);
 	}
 }
 
-fn recompile_scripts(
-	scripts: Vec<(String, Script)>,
-	n_scripts: usize,
-) -> (Vec<u8>, Vec<u8>, Vec<(String, Vec<u8>)>) {
-	let (_, directory, scripts_concat, scripts) = scripts
-		.into_iter()
-		.map(|(path, it)| {
-			log::debug!("Serializing {path}.");
-			let serialized = it.binary_serialize();
-			(serialized.len(), path, serialized)
+const WATCH_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+fn watch_snapshot(dirs: &[&Path]) -> HashMap<std::path::PathBuf, std::time::SystemTime> {
+	dirs.iter()
+		.flat_map(|dir| {
+			walkdir::WalkDir::new(dir)
+				.into_iter()
+				.filter_map(Result::ok)
 		})
-		.fold(
-			(16 * n_scripts, vec![], vec![], vec![]),
-			|(script_start, mut directory, mut scripts_concat, mut scripts),
-			 (this_script_len, path, this_script)| {
-				directory.extend((script_start as u32).to_le_bytes());
-				directory.extend((this_script_len as u32).to_le_bytes());
-				directory.extend([0u8; 8].into_iter());
-
-				scripts.push((path, this_script.clone()));
-				scripts_concat.extend(this_script);
-
-				(
-					script_start + this_script_len,
-					directory,
-					scripts_concat,
-					scripts,
-				)
-			},
-		);
-	(directory, scripts_concat, scripts)
+		.filter(|entry| entry.file_type().is_file())
+		.filter(|entry| {
+			!entry
+				.path()
+				.components()
+				.any(|it| it.as_os_str() == crate::pack_cache::CACHE_DIR_NAME)
+		})
+		.filter_map(|entry| {
+			let modified = entry.metadata().ok()?.modified().ok()?;
+			Some((entry.into_path(), modified))
+		})
+		.collect()
+}
+
+/// Fails with every problem [`Script::validate`] finds, instead of letting serialization panic
+/// on the first one.
+fn validate_script(path: &Path, script: &Script) -> anyhow::Result<()> {
+	let errors = script.validate();
+	if errors.is_empty() {
+		return Ok(());
+	}
+
+	let errors = errors
+		.iter()
+		.map(|it| format!("  {it}"))
+		.collect::<Vec<_>>()
+		.join("\n");
+	Err(anyhow::anyhow!("{path} can't be serialized:\n{errors}"))
 }
 
-pub fn do_extract_command(data: Vec<u8>, outfile: &PathBuf, quirks: Quirks) {
+pub fn do_extract_command(data: Vec<u8>, outfile: &PathBuf, quirks: Quirks) -> anyhow::Result<()> {
 	let decompressed_data = lz77::lz77_decompress(&data);
 
 	let script_entries = parse_scenario(&decompressed_data);
@@ -120,23 +300,406 @@ pub fn do_extract_command(data: Vec<u8>, outfile: &PathBuf, quirks: Quirks) {
 	)
 	.unwrap();
 
-	script_entries.into_par_iter().for_each(|entry| {
-		let res = parse_script(&entry, quirks).and_then(|script| {
+	let outcomes: Vec<ParseOutcome> = script_entries
+		.into_par_iter()
+		.filter_map(|entry| {
+			let (script, outcome) = match parse_script(&entry, quirks) {
+				Ok(parsed) => parsed,
+				Err(e) => {
+					log::error!("Encountered an error when parsing {}: {}", entry.name, e);
+					return Some(ParseOutcome::UnexpectedFailure);
+				}
+			};
+
 			let script_yaml = script2yaml(&script);
-			std::fs::write(
+			let res = std::fs::write(
 				&outfile.join(&entry.name).with_extension("yaml"),
 				script_yaml,
-			)
-			.map_err(anyhow::Error::new)
-		});
+			);
+
+			match res {
+				Ok(()) => Some(outcome),
+				Err(e) => {
+					log::error!("Encountered an error when writing {}: {}", entry.name, e);
+					None
+				}
+			}
+		})
+		.collect();
+
+	report_parse_outcomes(&outcomes)
+}
+
+/// The scenario's contents, decompressed unless it was packed without compression.
+fn scenario_bytes(data: Vec<u8>, raw: bool) -> Vec<u8> {
+	if raw {
+		data
+	} else {
+		lz77::lz77_decompress(&data)
+	}
+}
 
-		if let Err(e) = res {
-			log::error!("Encountered an error when writing {}: {}", entry.name, e);
+/// Describes the first and last opcodes of an entry, or why it couldn't be disassembled.
+fn opcode_summary(entry: &DirEntry, quirks: Quirks) -> (String, String) {
+	let describe =
+		|opcode: &OpcodeView| format!("0x{:02X} @ 0x{:08X}", opcode.opcode(), opcode.address);
+
+	let opcodes = match OpcodeIter::new(entry.data.unwrap_or_default(), quirks) {
+		Ok(opcodes) => opcodes,
+		Err(e) => return (format!("(error: {e})"), "-".to_string()),
+	};
+
+	let mut first = None;
+	let mut last = None;
+	for opcode in opcodes {
+		match opcode {
+			Ok(opcode) => {
+				first.get_or_insert(opcode);
+				last = Some(opcode);
+			}
+			Err(e) if first.is_none() => return (format!("(error: {e})"), "-".to_string()),
+			Err(_) => return (describe(&first.unwrap()), "(incomplete)".to_string()),
+		}
+	}
+	match (first, last) {
+		(Some(first), Some(last)) => (describe(&first), describe(&last)),
+		_ => ("-".to_string(), "-".to_string()),
+	}
+}
+
+fn spacing_note(spacing: Spacing) -> String {
+	match spacing {
+		Spacing::Adjacent => String::new(),
+		Spacing::Gap(size) => format!("0x{size:X} byte gap before"),
+		Spacing::Overlap(size) => format!("overlaps previous by 0x{size:X} bytes"),
+	}
+}
+
+/// Prints every entry in a scenario file, with its location, size and first and last opcodes.
+pub fn do_ls_command(data: Vec<u8>, raw: bool, quirks: Quirks) -> anyhow::Result<()> {
+	let data = scenario_bytes(data, raw);
+	let entries = parse_scenario(&data);
+	let spacing = entry_spacing(&entries);
+	let opcodes: Vec<_> = entries
+		.par_iter()
+		.map(|it| opcode_summary(it, quirks))
+		.collect();
+
+	println!(
+		"{:<6} {:<10} {:<10} {:<20} {:<20} notes",
+		"entry", "offset", "size", "first opcode", "last opcode"
+	);
+	for (idx, ((entry, spacing), (first, last))) in
+		entries.iter().zip(spacing).zip(opcodes).enumerate()
+	{
+		let mut notes = vec![spacing_note(spacing)];
+		if entry.extra != [0; 8] {
+			notes.push(format!(
+				"extra bytes {}",
+				entry
+					.extra
+					.iter()
+					.map(|it| format!("{it:02X}"))
+					.collect::<String>()
+			));
 		}
-	});
+		notes.retain(|it| !it.is_empty());
+
+		println!(
+			"{idx:<6} 0x{:08X} 0x{:08X} {first:<20} {last:<20} {}",
+			entry.offset,
+			entry.size,
+			notes.join(", ")
+		);
+	}
+
+	Ok(())
 }
 
-pub fn do_unpack_command(data: Vec<u8>, outfolder: &Path, scriptfolder: &Path, quirks: Quirks) {
+/// Prints a summary of a scenario file, or the details of a single entry if one is given.
+pub fn do_info_command(
+	data: Vec<u8>,
+	raw: bool,
+	entry: Option<usize>,
+	quirks: Quirks,
+) -> anyhow::Result<()> {
+	let file_size = data.len();
+	let data = scenario_bytes(data, raw);
+	let entries = parse_scenario(&data);
+	let spacing = entry_spacing(&entries);
+
+	if let Some(idx) = entry {
+		let entry = entries.get(idx).ok_or_else(|| {
+			anyhow::anyhow!(
+				"There's no entry {idx}, the scenario has {} entries.",
+				entries.len()
+			)
+		})?;
+		let (first, last) = opcode_summary(entry, quirks);
+		println!("Entry:        {idx} ({})", entry.name);
+		println!("Offset:       0x{:08X}", entry.offset);
+		println!("Size:         0x{:08X}", entry.size);
+		println!(
+			"Extra bytes:  {}",
+			entry
+				.extra
+				.iter()
+				.map(|it| format!("{it:02X}"))
+				.collect::<String>()
+		);
+		let spacing = match spacing[idx] {
+			Spacing::Adjacent => "right after the previous entry".to_string(),
+			spacing => spacing_note(spacing),
+		};
+		println!("Spacing:      {spacing}");
+		println!("First opcode: {first}");
+		println!("Last opcode:  {last}");
+		return Ok(());
+	}
+
+	let script_bytes: usize = entries.iter().map(|it| it.size).sum();
+	let gaps: Vec<usize> = spacing
+		.iter()
+		.filter_map(|it| match it {
+			Spacing::Gap(size) => Some(*size),
+			_ => None,
+		})
+		.collect();
+	let overlaps = spacing
+		.iter()
+		.filter(|it| matches!(it, Spacing::Overlap(_)))
+		.count();
+	let data_end = entries
+		.iter()
+		.map(|it| it.offset + it.size)
+		.max()
+		.unwrap_or_default();
+	let smallest = entries.iter().enumerate().min_by_key(|(_, it)| it.size);
+	let largest = entries.iter().enumerate().max_by_key(|(_, it)| it.size);
+
+	if raw {
+		println!("File size:          0x{file_size:08X} bytes (uncompressed)");
+	} else {
+		println!("Compressed size:    0x{file_size:08X} bytes");
+		println!(
+			"Decompressed size:  0x{:08X} bytes ({:.1}%)",
+			data.len(),
+			100.0 * file_size as f64 / data.len().max(1) as f64
+		);
+	}
+	println!(
+		"Entries:            {} (directory is 0x{:X} bytes)",
+		entries.len(),
+		16 * entries.len()
+	);
+	println!("Script data:        0x{script_bytes:08X} bytes");
+	if let (Some((small_idx, small)), Some((large_idx, large))) = (smallest, largest) {
+		println!(
+			"Smallest / largest: entry {small_idx} (0x{:X} bytes) / entry {large_idx} (0x{:X} bytes)",
+			small.size, large.size
+		);
+	}
+	println!(
+		"Gaps:               {}, 0x{:X} bytes in total",
+		gaps.len(),
+		gaps.iter().sum::<usize>()
+	);
+	println!("Overlaps:           {overlaps}");
+	println!(
+		"After last entry:   0x{:X} bytes",
+		data.len().saturating_sub(data_end)
+	);
+	println!(
+		"With extra bytes:   {} entries",
+		entries.iter().filter(|it| it.extra != [0; 8]).count()
+	);
+
+	Ok(())
+}
+
+/// Writes a single entry of a scenario file, either as raw bytes or as a yaml script.
+pub fn do_extract_entry_command(
+	data: Vec<u8>,
+	raw: bool,
+	entry: usize,
+	yaml: bool,
+	outfile: &Path,
+	quirks: Quirks,
+) -> anyhow::Result<()> {
+	let data = scenario_bytes(data, raw);
+	let entries = parse_scenario(&data);
+	let dir_entry = entries.get(entry).ok_or_else(|| {
+		anyhow::anyhow!(
+			"There's no entry {entry}, the scenario has {} entries.",
+			entries.len()
+		)
+	})?;
+
+	let output = if yaml {
+		let (script, _) = parse_script(dir_entry, quirks)?;
+		script2yaml(&script).into_bytes()
+	} else {
+		dir_entry.data.unwrap_or_default().to_vec()
+	};
+
+	write_atomic(outfile, &output).with_context(|| format!("Could not write {outfile}"))?;
+	log::info!("Wrote entry {entry} to {outfile}.");
+
+	Ok(())
+}
+
+/// Swaps some of the entries of a decompressed scenario for new scripts, keyed by entry number.
+/// Every other entry keeps its bytes and directory entry as they were, though entries after a
+/// changed one move, and any gaps between entries are closed up.
+pub fn patch_scenario(
+	data: &[u8],
+	replacements: &HashMap<usize, Vec<u8>>,
+) -> anyhow::Result<Vec<u8>> {
+	let entries = parse_scenario(data);
+	if let Some(idx) = replacements.keys().find(|&&it| it >= entries.len()) {
+		return Err(anyhow::anyhow!(
+			"There's no entry {idx}, the scenario has {} entries.",
+			entries.len()
+		));
+	}
+
+	let extras: Vec<[u8; 8]> = entries.iter().map(|it| it.extra).collect();
+	let scripts: Vec<&[u8]> = entries
+		.iter()
+		.enumerate()
+		.map(|(idx, entry)| match replacements.get(&idx) {
+			Some(replacement) => replacement.as_slice(),
+			None => entry.data.unwrap_or_default(),
+		})
+		.collect();
+
+	Ok(pack_scenario(&scripts, &extras, false))
+}
+
+/// Replaces the scripts at `script_paths` in an existing scenario file, leaving the rest alone.
+///
+/// Each script replaces the entry its file is named after, so `0045.yaml` replaces entry 45. If
+/// there's a translation document for it in `text_script_dir`, it's applied first.
+pub fn do_patch_command(
+	scenario: &Path,
+	script_paths: &[PathBuf],
+	text_script_dir: Option<&Path>,
+	outfile: &Path,
+	compressed: bool,
+) -> anyhow::Result<()> {
+	let replacements = script_paths
+		.iter()
+		.map(|path| {
+			let idx: usize = path
+				.file_stem()
+				.and_then(|it| it.parse().ok())
+				.ok_or_else(|| {
+					anyhow::anyhow!("Can't tell which entry {path} replaces, it should be named after it, like 0045.yaml.")
+				})?;
+
+			let yaml = std::fs::read(path).with_context(|| format!("Could not read {path}"))?;
+			let mut script: Script =
+				serde_yml::from_slice(&yaml).with_context(|| format!("Could not parse {path}"))?;
+			if let Some(text_script_dir) = text_script_dir {
+				let text_path = text_script_dir
+					.join(path.file_name().unwrap_or_default())
+					.with_extension("txt");
+				if let Ok(text) = std::fs::read_to_string(&text_path) {
+					log::info!("Applying {text_path} to {path}.");
+					tl_reverse_transform_script(&mut script, &text);
+				}
+			}
+			validate_script(path, &script)?;
+
+			Ok((idx, script.binary_serialize()))
+		})
+		.collect::<anyhow::Result<HashMap<_, _>>>()?;
+
+	let data = std::fs::read(scenario).with_context(|| format!("Could not read {scenario}"))?;
+	let patched = patch_scenario(&scenario_bytes(data, !compressed), &replacements)?;
+	let output = if compressed {
+		lz77::lz77_compress(&patched)
+	} else {
+		patched
+	};
+
+	write_atomic(outfile, &output).with_context(|| format!("Could not write {outfile}"))?;
+	log::info!(
+		"Replaced {} scripts in {scenario}, written to {outfile}.",
+		replacements.len()
+	);
+
+	Ok(())
+}
+
+/// Marks patches made between decompressed scenario files, which have to be applied the same way.
+const PATCH_LZ77_METADATA: &str = "compression=lz77";
+
+/// Writes a BPS patch that turns the `original` scenario file into the `rebuilt` one.
+///
+/// With `decompressed`, the patch is made between the decompressed scenarios instead, which is
+/// usually far smaller since a change anywhere shifts the whole compressed stream. Applying it
+/// then decompresses the original, and compresses the result again.
+pub fn do_make_patch_command(
+	original: &Path,
+	rebuilt: &Path,
+	outfile: &Path,
+	decompressed: bool,
+) -> anyhow::Result<()> {
+	let read = |path: &Path| std::fs::read(path).with_context(|| format!("Could not read {path}"));
+	let (mut source, mut target) = (read(original)?, read(rebuilt)?);
+	let mut metadata = "";
+	if decompressed {
+		source = lz77::lz77_decompress(&source);
+		target = lz77::lz77_decompress(&target);
+		metadata = PATCH_LZ77_METADATA;
+	}
+
+	let patch = crate::bps::create(&source, &target, metadata);
+	write_atomic(outfile, &patch).with_context(|| format!("Could not write {outfile}"))?;
+	log::info!(
+		"Wrote a 0x{:X} byte patch from {original} to {rebuilt} to {outfile}.",
+		patch.len()
+	);
+
+	Ok(())
+}
+
+/// Applies a patch made by [`do_make_patch_command`] to an original scenario file.
+pub fn do_apply_patch_command(original: &Path, patch: &Path, outfile: &Path) -> anyhow::Result<()> {
+	let source = std::fs::read(original).with_context(|| format!("Could not read {original}"))?;
+	let patch_data = std::fs::read(patch).with_context(|| format!("Could not read {patch}"))?;
+
+	let decompressed = crate::bps::metadata(&patch_data)
+		.with_context(|| format!("Could not read {patch}"))?
+		.lines()
+		.any(|it| it == PATCH_LZ77_METADATA);
+	let source = if decompressed {
+		lz77::lz77_decompress(&source)
+	} else {
+		source
+	};
+
+	let (output, _) = crate::bps::apply(&source, &patch_data)
+		.with_context(|| format!("Could not apply {patch} to {original}"))?;
+	let output = if decompressed {
+		lz77::lz77_compress(&output)
+	} else {
+		output
+	};
+
+	write_atomic(outfile, &output).with_context(|| format!("Could not write {outfile}"))?;
+	log::info!("Patched {original}, written to {outfile}.");
+
+	Ok(())
+}
+
+pub fn do_unpack_command(
+	data: Vec<u8>,
+	outfolder: &Path,
+	scriptfolder: &Path,
+	quirks: Quirks,
+) -> anyhow::Result<()> {
 	let decompressed_data = lz77::lz77_decompress(&data);
 
 	let scripts = parse_scenario(&decompressed_data);
@@ -153,11 +716,35 @@ pub fn do_unpack_command(data: Vec<u8>, outfolder: &Path, scriptfolder: &Path, q
 	)
 	.expect("Couldn't write directory");
 
-	scripts.into_par_iter().for_each(|entry| {
-		let res = parse_script(&entry, quirks).and_then(|script| {
+	let parsed: Vec<Option<(Script, ParseOutcome)>> = scripts
+		.par_iter()
+		.map(|entry| match parse_script(entry, quirks) {
+			Ok(parsed) => Some(parsed),
+			Err(e) => {
+				log::error!("Encountered an error when parsing {}: {}", entry.name, e);
+				None
+			}
+		})
+		.collect();
+
+	let unreachable = unpacked_reachability(outfolder, &parsed);
+
+	let outcomes: Vec<ParseOutcome> = scripts
+		.into_par_iter()
+		.zip(parsed)
+		.enumerate()
+		.filter_map(|(idx, (entry, parsed))| {
+			// Scripts that couldn't be parsed at all have already been reported, but still count
+			// against the exit code.
+			let Some((script, outcome)) = parsed else {
+				return Some(ParseOutcome::UnexpectedFailure);
+			};
 			let script_yaml = script2yaml(&script);
-			let script_txt = tl_transform_script(&script);
-			std::fs::write(
+			let script_txt = tl_transform_script_with_unreachable(
+				&script,
+				unreachable.get(idx).unwrap_or(&HashSet::new()),
+			);
+			let res = std::fs::write(
 				&outfolder.join(&entry.name).with_extension("yaml"),
 				script_yaml,
 			)
@@ -166,23 +753,481 @@ pub fn do_unpack_command(data: Vec<u8>, outfolder: &Path, scriptfolder: &Path, q
 					&scriptfolder.join(&entry.name).with_extension("txt"),
 					script_txt,
 				)
-			})
-			.map_err(anyhow::Error::new)
-		});
+			});
+
+			match res {
+				Ok(()) => Some(outcome),
+				Err(e) => {
+					log::error!("Encountered an error when writing {}: {}", entry.name, e);
+					None
+				}
+			}
+		})
+		.collect();
+
+	report_parse_outcomes(&outcomes)
+}
+
+/// Finds the unreachable text in a freshly unpacked scenario, and records it as the baseline
+/// that later edits are checked against. Returns the addresses of unreachable text per script.
+fn unpacked_reachability(
+	outfolder: &Path,
+	parsed: &[Option<(Script, ParseOutcome)>],
+) -> Vec<HashSet<u32>> {
+	use crate::reachability::{find_unreachable, unreachable_text, write_baseline};
+
+	if parsed.iter().any(Option::is_none) {
+		log::warn!("Skipping reachability analysis, since some scripts could not be parsed.");
+		return vec![];
+	}
+
+	let unreachable = find_unreachable(parsed.iter().flatten().map(|(it, _)| it), None);
+	if let Err(e) = write_baseline(outfolder, &unreachable) {
+		log::warn!("{e:#}");
+	}
+
+	let text = unreachable_text(&unreachable);
+	log::info!(
+		"Found {} lines of text that can't be reached.",
+		text.values().map(Vec::len).sum::<usize>()
+	);
+
+	(0..parsed.len())
+		.map(|idx| {
+			text.get(&idx)
+				.map(|it| it.iter().copied().collect())
+				.unwrap_or_default()
+		})
+		.collect()
+}
+
+/// The names of the scripts listed in `directory.yaml`, in directory order.
+fn directory_names(top_dir: &Path) -> anyhow::Result<Vec<String>> {
+	let directory_path = top_dir.join("directory.yaml");
+	let data = std::fs::read_to_string(&directory_path)
+		.with_context(|| format!("Could not read {directory_path}"))?;
+	let directory: Vec<DirEntry> =
+		serde_yml::from_str(&data).with_context(|| format!("Could not parse {directory_path}"))?;
+
+	Ok(directory.into_iter().map(|it| it.name).collect())
+}
+
+/// Loads every script listed in `directory.yaml`, in directory order, applying the translation
+/// documents in `text_script_dir` if one is given.
+pub fn load_scenario(
+	top_dir: &Path,
+	text_script_dir: Option<&Path>,
+) -> anyhow::Result<Vec<Script>> {
+	directory_names(top_dir)?
+		.into_par_iter()
+		.map(|name| {
+			let path = top_dir.join(&name);
+			let yaml = std::fs::read(&path).with_context(|| format!("Could not read {path}"))?;
+			let mut script: Script =
+				serde_yml::from_slice(&yaml).with_context(|| format!("Could not parse {path}"))?;
+			if let Some(text_script_dir) = text_script_dir {
+				let text_path = text_script_dir.join(&name).with_extension("txt");
+				if let Ok(text) = std::fs::read_to_string(text_path) {
+					tl_reverse_transform_script(&mut script, &text);
+				}
+			}
+			Ok(script)
+		})
+		.collect()
+}
+
+/// Loads every script of a scenario, either from a scenario file or from a directory it was
+/// unpacked to.
+fn load_any_scenario(path: &Path, raw: bool, quirks: Quirks) -> anyhow::Result<Vec<Script>> {
+	if path.join("directory.yaml").is_file() {
+		return load_scenario(path, None);
+	}
+
+	let data = std::fs::read(path).with_context(|| format!("Could not read {path}"))?;
+	let data = scenario_bytes(data, raw);
+	parse_scenario(&data)
+		.par_iter()
+		.map(|entry| {
+			parse_script(entry, quirks)
+				.map(|(script, _)| script)
+				.with_context(|| format!("Could not disassemble {} in {path}", entry.name))
+		})
+		.collect()
+}
 
-		if let Err(e) = res {
-			log::error!("Encountered an error when writing {}: {}", entry.name, e);
+/// Compares two scenarios, each either a scenario file or an unpacked directory, and writes
+/// the differences to `outfile`, or prints them.
+pub fn do_diff_command(
+	old: &Path,
+	old_options: (bool, Quirks),
+	new: &Path,
+	new_options: (bool, Quirks),
+	outfile: Option<&Path>,
+) -> anyhow::Result<()> {
+	use crate::scenario_diff::{diff_report, diff_scenarios};
+
+	let old_scenario = load_any_scenario(old, old_options.0, old_options.1)?;
+	let new_scenario = load_any_scenario(new, new_options.0, new_options.1)?;
+
+	let diffs = diff_scenarios(&old_scenario, &new_scenario);
+	let report = diff_report(&diffs, old_scenario.len().max(new_scenario.len()));
+
+	match outfile {
+		Some(outfile) => {
+			write_atomic(outfile, report.as_bytes())
+				.with_context(|| format!("Could not write {outfile}"))?;
+			log::info!("{} scripts differ, written to {outfile}.", diffs.len());
 		}
-	});
+		None => print!("{report}"),
+	}
+
+	Ok(())
+}
+
+/// Carries the translation of one unpacked release over to another, writing the ported lines
+/// into the target's yaml scripts and translation documents. Translated lines that couldn't be
+/// placed are listed in `report`, as CSV.
+pub fn do_port_command(
+	source_dir: &Path,
+	source_text_dir: &Path,
+	target_dir: &Path,
+	target_text_dir: &Path,
+	report: &Path,
+) -> anyhow::Result<()> {
+	use crate::port::port_translations;
+	use crate::reachability::read_baseline;
+	use crate::util::csv_field;
+
+	// The translation documents are read as they're written, without splitting pages or placing
+	// tips the way packing does, so what's ported is what the translator wrote.
+	let read_translations = |top_dir: &Path, text_dir: &Path| -> anyhow::Result<Vec<Script>> {
+		let names = directory_names(top_dir)?;
+		let mut scenario = load_scenario(top_dir, None)?;
+		for (script, name) in scenario.iter_mut().zip(&names) {
+			let text_path = text_dir.join(name).with_extension("txt");
+			if let Ok(text) = std::fs::read_to_string(text_path) {
+				for (address, (level, text)) in read_translation_doc(script, &text) {
+					place_tip(script, address, level, &text);
+				}
+			}
+		}
+		Ok(scenario)
+	};
+
+	let source = read_translations(source_dir, source_text_dir)?;
+	let mut target = read_translations(target_dir, target_text_dir)?;
+	let mut target_yaml = load_scenario(target_dir, None)?;
+	let names = directory_names(target_dir)?;
+
+	let summary = port_translations(&source, &target);
+	summary.apply(&mut target);
+	summary.apply(&mut target_yaml);
+	for tip in &summary.tips {
+		place_tip(&mut target[tip.script], tip.address, tip.level, &tip.text);
+	}
+
+	// Text that was unreachable when the target was unpacked stays marked as such.
+	let unreachable = read_baseline(target_dir).unwrap_or_default();
+	std::fs::create_dir_all(target_text_dir)
+		.with_context(|| format!("Could not create {target_text_dir}"))?;
+	for &idx in summary.ported.keys() {
+		// Only the ported translations and notes go in the yaml script, everything else the
+		// target's translation documents had stays in them.
+		let yaml_path = target_dir.join(&names[idx]).with_extension("yaml");
+		write_atomic(&yaml_path, script2yaml(&target_yaml[idx]).as_bytes())
+			.with_context(|| format!("Could not write {yaml_path}"))?;
+
+		let unreachable: HashSet<u32> = unreachable
+			.get(&idx)
+			.into_iter()
+			.flatten()
+			.copied()
+			.collect();
+		let text_path = target_text_dir.join(&names[idx]).with_extension("txt");
+		write_atomic(
+			&text_path,
+			tl_transform_script_with_unreachable(&target[idx], &unreachable).as_bytes(),
+		)
+		.with_context(|| format!("Could not write {text_path}"))?;
+	}
+
+	let mut output = String::from("script,address,kind,original,translation\n");
+	for line in &summary.unplaced {
+		output.push_str(&format!(
+			"{},0x{:08X},{},{},{}\n",
+			line.script,
+			line.address,
+			if line.tip { "tip" } else { "line" },
+			csv_field(&line.original),
+			csv_field(&unescape_str(&line.translation))
+		));
+	}
+	write_atomic(report, output.as_bytes()).with_context(|| format!("Could not write {report}"))?;
+
+	log::info!(
+		"Ported {} lines and tips into {} scripts. {} couldn't be placed, listed in {report}.",
+		summary.ported.values().sum::<usize>(),
+		summary.ported.len(),
+		summary.unplaced.len()
+	);
+
+	Ok(())
+}
+
+/// Plays through the scenario on the terminal, starting from script `start`, asking on stdin
+/// whenever the script presents a choice.
+pub fn do_play_command(
+	top_dir: &Path,
+	text_script_dir: Option<&Path>,
+	start: usize,
+	tip_level: u8,
+) -> anyhow::Result<()> {
+	use crate::interpreter::{Interpreter, Stop};
+	use std::io::BufRead;
+
+	let scenario = load_scenario(top_dir, text_script_dir)?;
+	let mut interpreter = Interpreter::new(&scenario, start)?.with_tip_level(tip_level);
+	let mut stdin = std::io::stdin().lock();
+
+	loop {
+		let stop = interpreter.run(|line| match line.speaker {
+			Some(speaker) => println!("{speaker}: {}", line.text),
+			None => println!("{}", line.text),
+		})?;
+
+		match stop {
+			Stop::Choice(choices) => {
+				for (idx, choice) in choices.iter().enumerate() {
+					println!("  {}. {choice}", idx + 1);
+				}
+
+				let choice = loop {
+					let mut input = String::new();
+					if stdin.read_line(&mut input)? == 0 {
+						return Ok(());
+					}
+					match input.trim().parse::<usize>() {
+						Ok(n) if (1..=choices.len()).contains(&n) => break n - 1,
+						_ => println!("Pick a number between 1 and {}.", choices.len()),
+					}
+				};
+				interpreter.choose(choice)?;
+			}
+			Stop::End => return Ok(()),
+			Stop::StepLimit => {
+				let (script, address) = interpreter.position();
+				return Err(anyhow::anyhow!(
+					"Gave up after too many steps in script {script} at {address:08X?}, this is probably an infinite loop."
+				));
+			}
+		}
+	}
+}
+
+/// Runs the route described by `choices_file` and prints its transcript, or checks it against a
+/// golden transcript if one is given. A missing golden transcript is created, and `bless`
+/// overwrites an existing one.
+pub fn do_route_command(
+	top_dir: &Path,
+	text_script_dir: Option<&Path>,
+	start: usize,
+	choices_file: &Path,
+	golden: Option<&Path>,
+	bless: bool,
+) -> anyhow::Result<()> {
+	use crate::interpreter::{parse_choice_sequence, run_route};
+
+	let choices = std::fs::read_to_string(choices_file)
+		.with_context(|| format!("Could not read {choices_file}"))?;
+	let choices = parse_choice_sequence(&choices)
+		.with_context(|| format!("Could not parse {choices_file}"))?;
+
+	let scenario = load_scenario(top_dir, text_script_dir)?;
+	let transcript = run_route(&scenario, start, &choices, u8::MAX)?;
+
+	let Some(golden) = golden else {
+		for line in transcript {
+			println!("{line}");
+		}
+		return Ok(());
+	};
+
+	if bless || !golden.exists() {
+		let mut data = transcript.join("\n");
+		data.push('\n');
+		write_atomic(golden, data.as_bytes())
+			.with_context(|| format!("Could not write {golden}"))?;
+		log::info!("Wrote {} lines to {golden}.", transcript.len());
+		return Ok(());
+	}
+
+	let expected =
+		std::fs::read_to_string(golden).with_context(|| format!("Could not read {golden}"))?;
+	let expected: Vec<&str> = expected.lines().collect();
+
+	let prefix = expected
+		.iter()
+		.zip(&transcript)
+		.take_while(|(a, b)| **a == b.as_str())
+		.count();
+	if prefix == expected.len() && prefix == transcript.len() {
+		log::info!("The route matches {golden}.");
+		return Ok(());
+	}
+
+	let suffix = expected[prefix..]
+		.iter()
+		.rev()
+		.zip(transcript[prefix..].iter().rev())
+		.take_while(|(a, b)| **a == b.as_str())
+		.count();
+
+	let mut report = format!(
+		"The route differs from {golden}, starting at line {}:\n",
+		prefix + 1
+	);
+	for line in &expected[prefix..expected.len() - suffix] {
+		report.push_str(&format!("- {line}\n"));
+	}
+	for line in &transcript[prefix..transcript.len() - suffix] {
+		report.push_str(&format!("+ {line}\n"));
+	}
+	log::error!("{report}");
+
+	Err(anyhow::anyhow!(
+		"The route no longer matches {golden}. Pass --bless to accept the new transcript."
+	))
+}
+
+/// Writes a table of every voiced line in the scenario to `outfile`, as CSV.
+pub fn do_voice_command(
+	top_dir: &Path,
+	text_script_dir: Option<&Path>,
+	outfile: &Path,
+) -> anyhow::Result<()> {
+	use crate::voice::{voice_csv, voice_lines};
+
+	let scenario = load_scenario(top_dir, text_script_dir)?;
+	let lines: Vec<_> = scenario
+		.iter()
+		.enumerate()
+		.flat_map(|(idx, script)| voice_lines(idx, script))
+		.collect();
+
+	write_atomic(outfile, voice_csv(&lines).as_bytes())
+		.with_context(|| format!("Could not write {outfile}"))?;
+
+	let unmatched = lines.iter().filter(|it| it.text_address.is_none()).count();
+	log::info!(
+		"Found {} voiced lines, written to {outfile}. {unmatched} voices weren't followed by any text.",
+		lines.len()
+	);
+
+	Ok(())
+}
+
+/// Writes every opcode that no path through the scenario reaches to `outfile` as CSV, and warns
+/// about text that has become unreachable since the scenario was unpacked.
+pub fn do_reach_command(
+	top_dir: &Path,
+	entries: Option<&[usize]>,
+	outfile: &Path,
+) -> anyhow::Result<()> {
+	use crate::reachability::{find_unreachable, newly_unreachable, read_baseline};
+	use crate::util::csv_field;
+
+	let scenario = load_scenario(top_dir, None)?;
+	let unreachable = find_unreachable(&scenario, entries);
+
+	let mut output = String::from("script,address,opcode,text\n");
+	for it in &unreachable {
+		output.push_str(&format!(
+			"{},0x{:08X},0x{:02X},{}\n",
+			it.script,
+			it.address,
+			it.opcode,
+			csv_field(it.text.as_deref().unwrap_or(""))
+		));
+	}
+	write_atomic(outfile, output.as_bytes())
+		.with_context(|| format!("Could not write {outfile}"))?;
+
+	let n_text = unreachable.iter().filter(|it| it.text.is_some()).count();
+	log::info!(
+		"Found {} unreachable opcodes, {n_text} of which show text, written to {outfile}.",
+		unreachable.len()
+	);
+
+	if entries.is_some() {
+		log::info!(
+			"Not comparing against the unpacked scenario, since custom entry points were given."
+		);
+		return Ok(());
+	}
+
+	match read_baseline(top_dir) {
+		Ok(baseline) => {
+			let new = newly_unreachable(&baseline, &unreachable);
+			for it in &new {
+				log::warn!(
+					"Text at 0x{:08X} in script {} can no longer be reached: {}",
+					it.address,
+					it.script,
+					it.text.as_deref().unwrap_or_default()
+				);
+			}
+			if new.is_empty() {
+				log::info!("No text has become unreachable since the scenario was unpacked.");
+			}
+		}
+		Err(e) => log::warn!("Not checking for newly unreachable text: {e:#}"),
+	}
+
+	Ok(())
 }
 
 pub(crate) fn script2yaml(script: &Script) -> String {
-	serde_yml::to_string(&script)
+	let yaml = serde_yml::to_string(&script)
 		.unwrap()
 		.replace("'[", "[")
 		.replace("]'", "]")
 		.replace(r#"'""#, "")
-		.replace(r#""'"#, "")
+		.replace(r#""'"#, "");
+
+	crate::xref::annotate_yaml(&yaml, &crate::xref::VARIABLE_NAMES)
+}
+
+/// Writes a cross-reference of every variable used in the scenario to `<outfile>.json` and
+/// `<outfile>.csv`.
+pub fn do_xref_command(top_dir: &Path, outfile: &Path) -> anyhow::Result<()> {
+	use crate::xref::{variable_uses, xref_csv, xref_json, VARIABLE_NAMES};
+
+	let scenario = load_scenario(top_dir, None)?;
+	let uses: Vec<_> = scenario
+		.iter()
+		.enumerate()
+		.flat_map(|(idx, script)| variable_uses(idx, script))
+		.collect();
+
+	for (extension, data) in [
+		("json", xref_json(&uses, &VARIABLE_NAMES)),
+		("csv", xref_csv(&uses, &VARIABLE_NAMES)),
+	] {
+		let path = outfile.with_extension(extension);
+		write_atomic(&path, data.as_bytes()).with_context(|| format!("Could not write {path}"))?;
+	}
+
+	let n_variables = uses
+		.iter()
+		.map(|it| it.variable)
+		.collect::<std::collections::HashSet<_>>()
+		.len();
+	log::info!(
+		"Found {} uses of {n_variables} variables, written to {outfile}.json and {outfile}.csv.",
+		uses.len()
+	);
+
+	Ok(())
 }
 
 pub fn do_reencode_command(outfile: &Path, filename: &Path) {
@@ -194,6 +1239,7 @@ pub fn do_reencode_command(outfile: &Path, filename: &Path) {
 
 	let script =
 		serde_yml::from_str::<Script>(&std::fs::read_to_string(&filename).unwrap()).unwrap();
+	validate_script(filename, &script).unwrap();
 	log::info!("Serializing {outfile}.");
 	std::fs::write(outfile, script.binary_serialize()).unwrap();
 }
@@ -239,6 +1285,7 @@ enum LineState {
 	Notes,
 	ChoiceTL,
 	ChoiceNotes,
+	Tip,
 }
 
 struct DocLine {
@@ -248,6 +1295,7 @@ struct DocLine {
 	translation: String,
 	notes: String,
 	choices: Vec<(String, String)>,
+	tip: Option<(u8, String)>,
 }
 
 impl DocLine {
@@ -259,11 +1307,22 @@ impl DocLine {
 			translation: String::default(),
 			notes: String::default(),
 			choices: vec![],
+			tip: None,
 		}
 	}
 }
 
 pub fn tl_reverse_transform_script(script: &mut Script, tl_doc: &str) {
+	for (address, (level, text)) in read_translation_doc(script, tl_doc) {
+		place_tip(script, address, level, &text);
+	}
+	split_pages(script);
+}
+
+/// Reads the translations and notes in a translation document into `script` as they're written,
+/// and returns the tips in it by the address of their line. Unlike
+/// [`tl_reverse_transform_script`], nothing is done to prepare the script for packing.
+fn read_translation_doc(script: &mut Script, tl_doc: &str) -> Vec<(u32, (u8, String))> {
 	let mut text2addr: HashMap<u32, &mut Opcode> = HashMap::new();
 	for opcode in script.opcodes.iter_mut() {
 		if ![0x47, 0x46, 0x45, 0x86, 0x31, 0x32].contains(&opcode.opcode()) {
@@ -315,6 +1374,9 @@ pub fn tl_reverse_transform_script(script: &mut Script, tl_doc: &str) {
 			let text = line[14..].trim().to_string();
 			curr_line.translation = text;
 			line_state = LineState::TL;
+		} else if let Some((level, text)) = parse_tl_tip_line(line) {
+			curr_line.tip = Some((level, text.trim().to_string()));
+			line_state = LineState::Tip;
 		} else if line.starts_with("[notes]:") {
 			if line_state == LineState::TL {
 				line_state = LineState::Notes;
@@ -333,6 +1395,11 @@ pub fn tl_reverse_transform_script(script: &mut Script, tl_doc: &str) {
 				LineState::Nothing => continue,
 				LineState::TL => curr_line.translation.push_str(&("\n".to_string() + line)),
 				LineState::Notes => curr_line.notes.push_str(&("\n".to_string() + line)),
+				LineState::Tip => {
+					if let Some((_, tip)) = &mut curr_line.tip {
+						tip.push_str(&("\n".to_string() + line));
+					}
+				}
 				LineState::ChoiceTL => curr_line
 					.choices
 					.last_mut()
@@ -349,6 +1416,11 @@ pub fn tl_reverse_transform_script(script: &mut Script, tl_doc: &str) {
 		}
 	}
 
+	let tips: Vec<_> = doclines
+		.iter_mut()
+		.filter_map(|line| line.tip.take().map(|tip| (line.address, tip)))
+		.collect();
+
 	for line in doclines {
 		if line.speaker_address != 0 {
 			let speaker_op = text2addr.get_mut(&line.speaker_address);
@@ -445,6 +1517,230 @@ pub fn tl_reverse_transform_script(script: &mut Script, tl_doc: &str) {
 			_ => {}
 		}
 	}
+
+	tips
+}
+
+/// Splits translated textboxes at page breaks, and wherever they overflow if `auto_page_break`
+/// is on. Each page after the first is shown in a copy of the original textbox, preceded by the
+/// opcodes the script uses to move from one textbox to the next.
+///
+/// The new opcodes get addresses past the end of the script, so they don't share one with the
+/// line they were split from and no jump can land on them.
+fn split_pages(script: &mut Script) {
+	let auto = crate::config::get().auto_page_break.unwrap_or_default();
+	let mut next_address = script_end(script);
+
+	let mut idx = 0;
+	while idx < script.opcodes.len() {
+		let text = match &script.opcodes[idx] {
+			Opcode::OP_TEXTBOX_DISPLAY(text) => text,
+			opcode => {
+				if let Some((address, _, Some(translation))) = text_of(opcode) {
+					if translation.contains(TL_PAGE_BREAK) {
+						log::warn!("Only textboxes can be split into pages, the page break in the text at 0x{address:08X} will be shown as is.");
+					}
+				}
+				idx += 1;
+				continue;
+			}
+		};
+		let pages = match &text.translation {
+			Some(translation) => text_pages(translation, auto),
+			None => vec![],
+		};
+		if pages.len() < 2 {
+			idx += 1;
+			continue;
+		}
+
+		let text = text.clone();
+		let page_turn = page_turn(&text, &script.opcodes[idx + 1..]);
+		let mut contents = vec![];
+		for page in &pages[1..] {
+			contents.extend(page_turn.iter().cloned());
+			contents.push(Opcode::OP_TEXTBOX_DISPLAY(StringOpcode {
+				translation: Some(page.clone()),
+				notes: None,
+				..text.clone()
+			}));
+		}
+		for opcode in &mut contents {
+			set_page_address(opcode, next_address);
+			next_address += opcode.size() as u32;
+		}
+		log::debug!(
+			"Splitting the text at 0x{:08X} into {} textboxes.",
+			text.address,
+			pages.len()
+		);
+
+		if let Opcode::OP_TEXTBOX_DISPLAY(op) = &mut script.opcodes[idx] {
+			op.translation = pages.into_iter().next();
+		}
+		script
+			.opcodes
+			.insert(idx + 1, Opcode::OP_Insert(InsertOpcode { contents }));
+		idx += 2;
+	}
+}
+
+/// The address right after the last opcode of the script and its footer.
+fn script_end(script: &Script) -> u32 {
+	let opcodes_end = script
+		.opcodes
+		.iter()
+		.filter(|it| it.address() != u32::MAX)
+		.map(|it| it.address() + it.size() as u32)
+		.max()
+		.unwrap_or(script.header.bytes.len() as u32);
+	opcodes_end + script.footer.bytes.len() as u32
+}
+
+/// Moves one of the opcodes [`split_pages`] adds to `address`.
+fn set_page_address(opcode: &mut Opcode, address: u32) {
+	match opcode {
+		Opcode::OP_TEXTBOX_DISPLAY(op) => op.address = address,
+		Opcode::OP_WAIT(op) => op.address = address,
+		Opcode::OP_6A(op) | Opcode::OP_CLEAR_SCREEN(op) | Opcode::OP_4F(op) => op.address = address,
+		opcode => unreachable!("{opcode:?} isn't part of a page"),
+	}
+}
+
+/// Splits translated text into the pages it's shown on: at every page break, and when `auto` is
+/// set, every few lines so no page has more lines than a textbox does.
+fn text_pages(text: &str, auto: bool) -> Vec<String> {
+	let trim = |page: &str| {
+		let mut page = page.trim();
+		while let Some(rest) = page.strip_prefix("%N").or_else(|| page.strip_suffix("%N")) {
+			page = rest.trim();
+		}
+		page.to_string()
+	};
+
+	let pages = text.split(TL_PAGE_BREAK).map(trim);
+	if !auto {
+		return pages.collect();
+	}
+
+	pages
+		.flat_map(|page| {
+			let page = if page.contains("%N") {
+				page
+			} else {
+				fix_string(&page)
+			};
+			page.split("%N")
+				.collect::<Vec<_>>()
+				.chunks(crate::config::TEXTBOX_LINES)
+				.map(|lines| lines.join("%N"))
+				.collect::<Vec<_>>()
+		})
+		.collect()
+}
+
+/// The opcodes that come between a textbox and the next one, copied from those after the
+/// textbox being split where possible, so the new pages behave like the rest of the script.
+fn page_turn(text: &StringOpcode, following: &[Opcode]) -> Vec<Opcode> {
+	let page_turn: Vec<_> = following
+		.iter()
+		.take_while(|it| {
+			matches!(
+				it,
+				Opcode::OP_WAIT(_)
+					| Opcode::OP_6A(_)
+					| Opcode::OP_CLEAR_SCREEN(_)
+					| Opcode::OP_4F(_)
+			)
+		})
+		.cloned()
+		.collect();
+	if page_turn.iter().any(|it| matches!(it, Opcode::OP_WAIT(_))) {
+		return page_turn;
+	}
+
+	let address = text.address;
+	vec![
+		Opcode::OP_WAIT(BasicOpcode2 {
+			address,
+			actual_address: 0,
+			opcode: 0x4A,
+			arg1: 0xFFFF,
+		}),
+		Opcode::OP_CLEAR_SCREEN(BasicOpcode4 {
+			address,
+			actual_address: 0,
+			opcode: 0x49,
+			arg1: 0xFFFF,
+			arg2: 0xFFFF,
+		}),
+		Opcode::OP_4F(BasicOpcode4 {
+			address,
+			actual_address: 0,
+			opcode: 0x4F,
+			arg1: 0x000D,
+			arg2: 0x0000,
+		}),
+	]
+}
+
+/// Adds a tip after the line at `address`, or updates the one that's already there. An empty tip
+/// removes it instead.
+///
+/// New tips go after the wait that follows the line, so they're shown once it's been read.
+fn place_tip(script: &mut Script, address: u32, level: u8, text: &str) {
+	let text = escape_str(text.trim());
+	let existing = script
+		.opcodes
+		.iter()
+		.position(|it| matches!(it, Opcode::Tip(tip) if tip.address == address));
+
+	match (existing, text.is_empty()) {
+		(Some(idx), true) => {
+			script.opcodes.remove(idx);
+		}
+		(Some(idx), false) => {
+			if let Opcode::Tip(tip) = &mut script.opcodes[idx] {
+				tip.level = level;
+				tip.text = text;
+			}
+		}
+		(None, true) => {}
+		(None, false) => {
+			let Some(line) = script.opcodes.iter().position(|it| it.address() == address) else {
+				log::warn!("There's no line at 0x{address:08X} to add a tip to.");
+				return;
+			};
+
+			let mut idx = line + 1;
+			for (offset, opcode) in script.opcodes[line + 1..].iter().enumerate() {
+				if matches!(opcode, Opcode::OP_WAIT(_)) {
+					idx = line + offset + 2;
+					break;
+				}
+				if text_of(opcode).is_some() || [0x31, 0x32].contains(&opcode.opcode()) {
+					break;
+				}
+			}
+
+			script.opcodes.insert(
+				idx,
+				Opcode::Tip(TipOpcode {
+					address,
+					actual_address: 0,
+					level,
+					text,
+				}),
+			);
+		}
+	}
+}
+
+/// Parses `[tip level=1]: text` lines, returning the level and the text.
+fn parse_tl_tip_line(line: &str) -> Option<(u8, &str)> {
+	let rest = line.strip_prefix("[tip level=")?;
+	let (level, text) = rest.split_once("]:")?;
+	Some((level.trim().parse().ok()?, text))
 }
 
 fn parse_tl_doc_line(line: &str, prefix_size: usize, is_speaker: bool) -> (u32, String) {
@@ -484,12 +1780,35 @@ fn parse_tl_doc_line(line: &str, prefix_size: usize, is_speaker: bool) -> (u32,
 
 const TL_CHOICE_END: Lazy<String> = Lazy::new(|| "---~~~---".to_string());
 const TL_LINE_END: Lazy<String> = Lazy::new(|| "---===---".to_string());
+const TL_UNREACHABLE: &str = "[unreachable]: Nothing in the scenario leads here.";
+/// Starts a new textbox in translated text.
+const TL_PAGE_BREAK: &str = "%P";
 
 pub fn tl_transform_script(input: &Script) -> String {
+	tl_transform_script_with_unreachable(input, &HashSet::new())
+}
+
+/// Like [`tl_transform_script`], but flags the text at the `unreachable` addresses as never
+/// being shown, so translators know they can leave it for last.
+pub fn tl_transform_script_with_unreachable(input: &Script, unreachable: &HashSet<u32>) -> String {
 	let mut lines = vec![];
 
+	let tips: HashMap<u32, &TipOpcode> = input
+		.opcodes
+		.iter()
+		.filter_map(|it| match it {
+			Opcode::Tip(tip) => Some((tip.address, tip)),
+			_ => None,
+		})
+		.collect();
+
 	let mut curr_speaker = ("", String::default(), &0);
+	let mut curr_voice = None;
 	for opcode in input.opcodes.iter() {
+		if let Opcode::OP_PLAY_VOICE(voice) = opcode {
+			curr_voice = Some(voice);
+			continue;
+		}
 		if ![0x47, 0x46, 0x45, 0x86, 0x31, 0x32].contains(&opcode.opcode()) {
 			continue;
 		}
@@ -550,6 +1869,12 @@ pub fn tl_transform_script(input: &Script) -> String {
 						continue;
 					} else {
 						// lines.push(format!("index {}", i + 1));
+						if unreachable.contains(address) {
+							lines.push(TL_UNREACHABLE.to_string());
+						}
+						if let Some(voice) = curr_voice.take() {
+							lines.push(tl_voice_line(voice));
+						}
 						if !curr_speaker.0.is_empty() {
 							lines.push(format!(
 								"[speaker @ 0x{:08X}]: {} ({})",
@@ -580,6 +1905,12 @@ pub fn tl_transform_script(input: &Script) -> String {
 						.unwrap_or_default();
 
 					// lines.push(format!("index {}", i + 1));
+					if unreachable.contains(address) {
+						lines.push(TL_UNREACHABLE.to_string());
+					}
+					if let Some(voice) = curr_voice.take() {
+						lines.push(tl_voice_line(voice));
+					}
 					if !curr_speaker.0.is_empty() {
 						lines.push(format!(
 							"[speaker @ 0x{:08X}]: {} ({})",
@@ -611,6 +1942,12 @@ pub fn tl_transform_script(input: &Script) -> String {
 						.map(|it| unescape_str(it.as_str()))
 						.unwrap_or_default();
 
+					if unreachable.contains(address) {
+						lines.push(TL_UNREACHABLE.to_string());
+					}
+					if let Some(voice) = curr_voice.take() {
+						lines.push(tl_voice_line(voice));
+					}
 					if !curr_speaker.0.is_empty() {
 						lines.push(format!(
 							"[speaker @ 0x{:08X}]: {} ({})",
@@ -639,6 +1976,12 @@ pub fn tl_transform_script(input: &Script) -> String {
 						.map(|it| unescape_str(it.as_str()))
 						.unwrap_or_default();
 
+					if unreachable.contains(address) {
+						lines.push(TL_UNREACHABLE.to_string());
+					}
+					if let Some(voice) = curr_voice.take() {
+						lines.push(tl_voice_line(voice));
+					}
 					if !curr_speaker.0.is_empty() {
 						lines.push(format!(
 							"[speaker @ 0x{:08X}]: {} ({})",
@@ -661,6 +2004,9 @@ pub fn tl_transform_script(input: &Script) -> String {
 				}) = opcode
 				{
 					// lines.push(format!("index {}", i + 1));
+					if unreachable.contains(address) {
+						lines.push(TL_UNREACHABLE.to_string());
+					}
 					lines.push(format!("[choices @ 0x{address:08X}]"));
 					for (
 						j,
@@ -689,6 +2035,13 @@ pub fn tl_transform_script(input: &Script) -> String {
 			}
 			_ => continue,
 		}
+		if let Some(tip) = tips.get(&opcode.address()) {
+			lines.push(format!(
+				"[tip level={}]: {}",
+				tip.level,
+				unescape_str(&tip.text)
+			));
+		}
 		lines.push(TL_LINE_END.clone());
 		lines.push("\n".to_string());
 	}
@@ -696,6 +2049,15 @@ pub fn tl_transform_script(input: &Script) -> String {
 	lines.join("\n")
 }
 
+/// The voice reference shown above voiced lines in translation documents. It's only there for
+/// reference, and is ignored when reading the document back.
+fn tl_voice_line(voice: &Op44Opcode) -> String {
+	format!(
+		"[voice @ 0x{:08X}]: type 0x{:04X}, id 0x{:04X}",
+		voice.address, voice.arg1, voice.arg2
+	)
+}
+
 pub fn do_fix_command(input_file: &PathBuf, outfile: PathBuf) {
 	let data = std::fs::read_to_string(input_file).unwrap();
 	let mut output = vec![];
@@ -711,9 +2073,16 @@ pub fn do_fix_command(input_file: &PathBuf, outfile: PathBuf) {
 
 #[cfg(test)]
 mod test {
-	use crate::opcodescript::Script;
+	use crate::opcodescript::{Opcode, Quirks, Script};
+
+	use std::collections::HashMap;
 
-	use super::{tl_reverse_transform_script, tl_transform_script};
+	use super::{
+		archive_scenario, do_assemble_command, patch_scenario, read_translation_doc, script2yaml,
+		text_pages, tl_reverse_transform_script, tl_transform_script, PackOutput,
+	};
+	use crate::scenario_pack::{parse_scenario, DirEntry};
+	use camino::Utf8PathBuf;
 
 	#[test]
 	fn test_transform() {
@@ -729,4 +2098,214 @@ mod test {
 		tl_reverse_transform_script(&mut new_script, &translated_str);
 		assert_eq!(new_script, script);
 	}
+
+	#[test]
+	fn test_tips() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00,
+			0x4A, 0xFF, 0xFF, // wait
+			0x05, 0x00, // return
+		];
+		let (mut script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		let doc = tl_transform_script(&script).replace(
+			"[notes]: \n",
+			"[notes]: \n[tip level=1]: First line\nsecond line\n",
+		);
+		tl_reverse_transform_script(&mut script, &doc);
+		assert!(
+			matches!(&script.opcodes[2], Opcode::Tip(tip) if tip.text == "First line%Nsecond line")
+		);
+		assert!(tl_transform_script(&script)
+			.contains("[tip level=1]: First line\nsecond line\n---===---"));
+
+		// Reading the same document again shouldn't add a second tip.
+		tl_reverse_transform_script(&mut script, &doc);
+		assert_eq!(script.opcodes.len(), 4);
+
+		let output = script.binary_serialize();
+		// The skip covers the 5 byte clear, the 5 byte 0x4F, the textbox and the 3 byte wait.
+		let text_size = 6 + "First line%Nsecond line".len();
+		assert_eq!(
+			output[14..18],
+			[0x77, 0x01, (4 + 5 + 5 + text_size + 3) as u8, 0x00]
+		);
+
+		let (script, error) = Script::new(&output, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+		assert!(matches!(&script.opcodes[2], Opcode::OP_CUSTOM_TIP_77(op) if op.skip == 4));
+	}
+
+	#[test]
+	fn test_page_breaks() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00,
+			0x4A, 0xFF, 0xFF, // wait
+			0x49, 0xFF, 0xFF, 0xFF, 0xFF, // clear
+			0x4F, 0x0D, 0x00, 0x00, 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00,
+			0x4A, 0xFF, 0xFF, // wait
+			0x05, 0x00, // return
+		];
+		let (mut script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		let doc = tl_transform_script(&script).replacen(
+			"[translation]: \n",
+			"[translation]: one\n%P\ntwo\n",
+			1,
+		);
+		tl_reverse_transform_script(&mut script, &doc);
+
+		// The new page is put past the end of the script, away from the line it was split from.
+		assert!(script.validate().is_empty());
+		let Opcode::OP_Insert(insert) = &script.opcodes[1] else {
+			panic!("Expected the new page to be inserted after the first one");
+		};
+		let addresses: Vec<u32> = insert.contents.iter().map(Opcode::address).collect();
+		assert_eq!(addresses, [0x24, 0x27, 0x2C, 0x31]);
+		#[rustfmt::skip]
+		assert_eq!(
+			script.binary_serialize(),
+			[
+				0x04, 0x00, 0x00, 0x00,
+				0x45, 0xFF, 0xFF, 0x14, 0x00, b'o', b'n', b'e', 0x00,
+				0x4A, 0xFF, 0xFF,
+				0x49, 0xFF, 0xFF, 0xFF, 0xFF,
+				0x4F, 0x0D, 0x00, 0x00, 0x00,
+				0x45, 0xFF, 0xFF, 0x14, 0x00, b't', b'w', b'o', 0x00,
+				0x4A, 0xFF, 0xFF,
+				0x49, 0xFF, 0xFF, 0xFF, 0xFF,
+				0x4F, 0x0D, 0x00, 0x00, 0x00,
+				0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00,
+				0x4A, 0xFF, 0xFF,
+				0x05, 0x00,
+			]
+		);
+
+		assert_eq!(text_pages("a%Nb%Nc%Nd%Pe", false), vec!["a%Nb%Nc%Nd", "e"]);
+		assert_eq!(text_pages("a%Nb%Nc%Nd%Pe", true), vec!["a%Nb%Nc", "d", "e"]);
+	}
+
+	#[test]
+	fn test_read_translation_doc() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00,
+			0x4A, 0xFF, 0xFF, // wait
+			0x05, 0x00, // return
+		];
+		let (mut script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		let doc = tl_transform_script(&script)
+			.replacen("[translation]: \n", "[translation]: one\n%P\ntwo\n", 1)
+			.replace("[notes]: \n", "[notes]: \n[tip level=2]: A tip\n");
+		let tips = read_translation_doc(&mut script, &doc);
+
+		// Nothing is split or inserted, the document is read as it's written.
+		assert_eq!(script.opcodes.len(), 3);
+		assert!(matches!(
+			&script.opcodes[0],
+			Opcode::OP_TEXTBOX_DISPLAY(op) if op.translation.as_deref() == Some("one%N%P%Ntwo")
+		));
+		assert_eq!(tips, vec![(0x04, (2, "A tip".to_string()))]);
+	}
+
+	#[test]
+	fn test_patch_scenario() {
+		#[rustfmt::skip]
+		let data = [
+			0x20, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+			0x22, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x09,
+			0x05, 0x00, // entry 0
+			0x4A, 0xFF, 0xFF, // entry 1
+		];
+
+		let replacements = HashMap::from([(0, vec![0x4A, 0xFF, 0xFF, 0x05, 0x00])]);
+		let patched = patch_scenario(&data, &replacements).unwrap();
+		let entries = parse_scenario(&patched);
+		assert_eq!(entries.len(), 2);
+		assert_eq!(
+			entries[0].data,
+			Some([0x4A, 0xFF, 0xFF, 0x05, 0x00].as_slice())
+		);
+		assert_eq!(entries[1].offset, 0x25);
+		assert_eq!(entries[1].data, Some([0x4A, 0xFF, 0xFF].as_slice()));
+		assert_eq!(entries[0].extra, [1, 2, 3, 4, 5, 6, 7, 8]);
+		assert_eq!(entries[1].extra, [0, 0, 0, 0, 0, 0, 0, 9]);
+
+		assert!(patch_scenario(&data, &HashMap::from([(2, vec![])])).is_err());
+	}
+
+	#[test]
+	fn test_loose_scripts_roundtrip() {
+		let dir = Utf8PathBuf::from_path_buf(std::env::temp_dir())
+			.unwrap()
+			.join(format!("yeti_loose_scripts_test_{}", std::process::id()));
+		let yaml_dir = dir.join("sn.bin.yaml");
+		std::fs::create_dir_all(&yaml_dir).unwrap();
+
+		#[rustfmt::skip]
+		let scripts: [&[u8]; 2] = [
+			&[
+				0x04, 0x00, 0x00, 0x00,
+				0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00,
+				0x4A, 0xFF, 0xFF,
+				0x05, 0x00,
+			],
+			&[0x04, 0x00, 0x00, 0x00, 0x05, 0x00],
+		];
+		let mut directory = vec![];
+		for (idx, data) in scripts.iter().enumerate() {
+			let (script, error) = Script::new(data, Quirks::CCFC).unwrap();
+			assert!(error.is_none());
+			let name = format!("{idx:04}.yaml");
+			std::fs::write(yaml_dir.join(&name), script2yaml(&script)).unwrap();
+			directory.push(DirEntry {
+				name,
+				offset: 0,
+				size: 0,
+				data: None,
+				extra: [0, 0, 0, 0, 0, 0, 0, idx as u8],
+			});
+		}
+		std::fs::write(
+			yaml_dir.join("directory.yaml"),
+			serde_yml::to_string(&directory).unwrap(),
+		)
+		.unwrap();
+
+		let text_dir = dir.join("sn.bin.script");
+		let packed = dir.join("sn.bin");
+		let loose = dir.join("sn.bin.opcodescript");
+		let assembled = dir.join("assembled.bin");
+		let compress = PackOutput::Scenario { compress: true };
+		archive_scenario(&yaml_dir, &text_dir, &packed, compress, false, true).unwrap();
+		archive_scenario(
+			&yaml_dir,
+			&text_dir,
+			&loose,
+			PackOutput::LooseScripts,
+			false,
+			true,
+		)
+		.unwrap();
+		assert_eq!(
+			std::fs::read(loose.join("0000.opcodescript")).unwrap(),
+			scripts[0]
+		);
+		do_assemble_command(&loose, &assembled, true).unwrap();
+
+		let packed = std::fs::read(packed).unwrap();
+		let assembled = std::fs::read(assembled).unwrap();
+		std::fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(assembled, packed);
+	}
 }
@@ -0,0 +1,192 @@
+//! Project configuration, read from a `yeti.toml` file.
+//!
+//! Every value is optional; anything left out falls back to the tool's built in defaults, and any
+//! flag given on the command line takes precedence over what's in the file.
+//!
+//! ```toml
+//! quirks = "lp"
+//! scenario = "sn.bin"
+//! yaml_dir = "extracted_scenario/sn.bin.yaml"
+//! text_dir = "extracted_scenario/sn.bin.script"
+//! output = "build/sn.bin"
+//! compress = true
+//! italic_map = "italic_map.json"
+//! textbox_width = 60
+//! auto_page_break = false
+//! recover = false
+//! variable_names = "variables.toml"
+//! expected_failures = [
+//!   1,
+//!   { script = 382, kind = "unknown-opcode", address = 0x1234 },
+//! ]
+//! ```
+
+use anyhow::{Context, Result};
+use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
+use once_cell::sync::OnceCell;
+use serde_derive::Deserialize;
+
+use crate::scenario_pack::KnownFailure;
+
+pub const CONFIG_FILE_NAME: &str = "yeti.toml";
+
+/// The default number of characters that fit on a single line of a textbox.
+pub const DEFAULT_TEXTBOX_WIDTH: usize = 60;
+
+/// The number of lines that fit in a textbox.
+pub const TEXTBOX_LINES: usize = 3;
+
+static PROJECT_CONFIG: OnceCell<ProjectConfig> = OnceCell::new();
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+	/// Comma separated quirks, in the same format as the `-q` flag.
+	pub quirks: Option<String>,
+	/// The original scenario file.
+	pub scenario: Option<PathBuf>,
+	/// Where disassembled yaml scripts and `directory.yaml` live.
+	pub yaml_dir: Option<PathBuf>,
+	/// Where translation documents live.
+	pub text_dir: Option<PathBuf>,
+	/// Where the repacked scenario file is written.
+	pub output: Option<PathBuf>,
+	/// Whether to lz77 compress the repacked scenario file.
+	pub compress: Option<bool>,
+	/// Glyph table used for italic text.
+	pub italic_map: Option<PathBuf>,
+	/// Characters per textbox line, used when wrapping text. Must be at least 1.
+	pub textbox_width: Option<usize>,
+	/// Split translations that don't fit in one textbox over as many as they need when packing.
+	pub auto_page_break: Option<bool>,
+	/// Keep undecodable parts of scripts as raw bytes and carry on disassembling after them.
+	pub recover: Option<bool>,
+	/// Names for script variables, used to annotate disassembled scripts and cross-references.
+	pub variable_names: Option<PathBuf>,
+	/// Scripts that are known not to disassemble cleanly. Replaces the built in list for the game.
+	pub expected_failures: Option<Vec<KnownFailure>>,
+}
+
+impl ProjectConfig {
+	/// Reads a config file. Relative paths inside it are resolved against the file's directory.
+	pub fn load(path: &Path) -> Result<Self> {
+		let data =
+			std::fs::read_to_string(path).with_context(|| format!("Could not read {path}"))?;
+		let mut config: Self =
+			toml::from_str(&data).with_context(|| format!("Could not parse {path}"))?;
+
+		if config.textbox_width == Some(0) {
+			return Err(anyhow::anyhow!(
+				"textbox_width in {path} must be at least 1"
+			));
+		}
+
+		let base = path.parent().unwrap_or(Path::new(""));
+		for path in [
+			&mut config.scenario,
+			&mut config.yaml_dir,
+			&mut config.text_dir,
+			&mut config.output,
+			&mut config.italic_map,
+			&mut config.variable_names,
+		]
+		.into_iter()
+		.flatten()
+		{
+			if path.is_relative() {
+				*path = base.join(&*path);
+			}
+		}
+
+		Ok(config)
+	}
+
+	/// Looks for a `yeti.toml` in `dir` and each of its parents, loading the first one found.
+	pub fn discover(dir: &Path) -> Result<Option<Self>> {
+		dir.ancestors()
+			.map(|it| it.join(CONFIG_FILE_NAME))
+			.find(|it| it.is_file())
+			.map(|it| {
+				log::info!("Using project configuration from {it}");
+				Self::load(&it)
+			})
+			.transpose()
+	}
+
+	pub fn textbox_width(&self) -> usize {
+		self.textbox_width.unwrap_or(DEFAULT_TEXTBOX_WIDTH)
+	}
+}
+
+/// Sets the configuration used for the rest of the program. Only the first call has any effect.
+pub fn init(config: ProjectConfig) {
+	if PROJECT_CONFIG.set(config).is_err() {
+		log::warn!("Project configuration was already initialised.");
+	}
+}
+
+/// The active project configuration, or the defaults if none was loaded.
+pub fn get() -> &'static ProjectConfig {
+	PROJECT_CONFIG.get_or_init(ProjectConfig::default)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::ProjectConfig;
+	use crate::scenario_pack::KnownFailure;
+	use camino::Utf8PathBuf;
+
+	#[test]
+	fn test_parse_config() {
+		let config: ProjectConfig = toml::from_str(
+			r#"
+quirks = "lp"
+yaml_dir = "sn.bin.yaml"
+compress = false
+textbox_width = 48
+auto_page_break = true
+expected_failures = [1, { script = 382, kind = "unknown-opcode", address = 0x10 }]
+"#,
+		)
+		.unwrap();
+
+		assert_eq!(config.quirks.as_deref(), Some("lp"));
+		assert_eq!(
+			config.yaml_dir.as_deref().map(|it| it.as_str()),
+			Some("sn.bin.yaml")
+		);
+		assert_eq!(config.compress, Some(false));
+		assert_eq!(config.textbox_width(), 48);
+		assert_eq!(config.auto_page_break, Some(true));
+		assert_eq!(
+			config.expected_failures,
+			Some(vec![
+				KnownFailure {
+					script: 1,
+					kind: None,
+					address: None,
+				},
+				KnownFailure {
+					script: 382,
+					kind: Some("unknown-opcode".to_string()),
+					address: Some(0x10),
+				},
+			])
+		);
+		assert_eq!(ProjectConfig::default().textbox_width(), 60);
+
+		assert!(toml::from_str::<ProjectConfig>("unknown = 1").is_err());
+	}
+
+	#[test]
+	fn test_zero_textbox_width() {
+		let path = Utf8PathBuf::from_path_buf(std::env::temp_dir())
+			.unwrap()
+			.join(format!("yeti_config_test_{}.toml", std::process::id()));
+		std::fs::write(&path, "textbox_width = 0\n").unwrap();
+		let error = ProjectConfig::load(&path).unwrap_err();
+		std::fs::remove_file(&path).unwrap();
+
+		assert!(error.to_string().contains("textbox_width"));
+	}
+}
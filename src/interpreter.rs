@@ -0,0 +1,566 @@
+//! A small virtual machine that steps through a scenario the way the game would, as far as we
+//! understand its opcodes, so a route can be read through without running the game.
+//!
+//! Only control flow, variables, text and choices are simulated. Everything else (graphics,
+//! sound, waits) is skipped.
+//!
+//! Nobody has documented what the variable opcodes actually do, so the following is a best
+//! guess based on how they're used in the scripts:
+//! - `0x10`-`0x17` apply `arg2` to variable `arg1`; in order: set, add, subtract, multiply,
+//!   divide, modulo, bitwise and, bitwise or.
+//! - Conditional jumps compare variable `arg1` against the value `arg2`; `JZ`/`JNZ` compare
+//!   variable `arg1` against zero.
+//! - `Switch` looks up variable `comparison_value`, and jumps to the arm with that index.
+//! - `OP_JUMP_TO_SCRIPT` calls into another script, and `OP_SCRIPT_RETURN` returns from it.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{anyhow, Result};
+
+use crate::opcodescript::{Opcode, Script};
+use crate::util::{escape_str, unescape_str};
+
+/// A line of text, as it would be shown on screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+	pub script: usize,
+	pub address: u32,
+	pub speaker: Option<String>,
+	pub text: String,
+}
+
+/// Why the interpreter stopped running.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Stop {
+	/// Waiting for one of these to be picked with [`Interpreter::choose`].
+	Choice(Vec<String>),
+	/// The route is over.
+	End,
+	/// Too many opcodes were run without reaching a choice or the end, which usually means a
+	/// loop that waits on something we don't simulate.
+	StepLimit,
+}
+
+/// How many opcodes [`Interpreter::run`] executes before giving up.
+pub const MAX_STEPS: usize = 1_000_000;
+
+const MAX_CALL_DEPTH: usize = 256;
+
+/// A script with its inserted opcodes flattened out, so it can be stepped through linearly.
+pub(crate) struct FlatScript<'a> {
+	pub(crate) opcodes: Vec<&'a Opcode>,
+	/// Maps original addresses to indices in `opcodes`.
+	pub(crate) addresses: HashMap<u32, usize>,
+}
+
+impl<'a> FlatScript<'a> {
+	pub(crate) fn new(script: &'a Script) -> Self {
+		let mut flat = FlatScript {
+			opcodes: vec![],
+			addresses: HashMap::new(),
+		};
+		flat.extend(&script.opcodes, false);
+		flat
+	}
+
+	fn extend(&mut self, opcodes: &'a [Opcode], inserted: bool) {
+		for opcode in opcodes {
+			let idx = self.opcodes.len();
+			match opcode {
+				Opcode::OP_Insert(insert) => {
+					self.extend(&insert.contents, true);
+					continue;
+				}
+				// Jumps to deleted or replaced opcodes land on whatever takes their place.
+				Opcode::OP_Delete(_) | Opcode::OP_Replace(_) => {
+					for address in opcode.jump_addresses() {
+						self.addresses.insert(address, idx);
+					}
+					if let Opcode::OP_Replace(replace) = opcode {
+						self.extend(&replace.replacement, true);
+					}
+					continue;
+				}
+				_ => {}
+			}
+
+			// Inserted opcodes tend to reuse addresses copied from elsewhere in the script, so the
+			// original opcodes take priority when resolving jumps. Tips borrow the address of
+			// their line, and have none to jump to.
+			for address in opcode.jump_addresses() {
+				if inserted {
+					self.addresses.entry(address).or_insert(idx);
+				} else {
+					self.addresses.insert(address, idx);
+				}
+			}
+			self.opcodes.push(opcode);
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Frame {
+	script: usize,
+	pc: usize,
+}
+
+pub struct Interpreter<'a> {
+	scripts: Vec<FlatScript<'a>>,
+	variables: HashMap<u16, i32>,
+	frame: Frame,
+	call_stack: Vec<Frame>,
+	speaker: Option<String>,
+	/// Jump targets for each option of the choice we're stopped at.
+	pending_choice: Option<Vec<u32>>,
+	/// Custom tips with a condition above this level are skipped.
+	tip_level: u8,
+	skipped_opcodes: HashSet<u8>,
+}
+
+impl<'a> Interpreter<'a> {
+	/// Sets up an interpreter that starts at the first opcode of script `start`.
+	///
+	/// `scenario` is indexed by script number, the same way `OP_JUMP_TO_SCRIPT` refers to them.
+	pub fn new(scenario: &'a [Script], start: usize) -> Result<Self> {
+		if start >= scenario.len() {
+			return Err(anyhow!(
+				"Script {start} doesn't exist, the scenario only has {} scripts.",
+				scenario.len()
+			));
+		}
+
+		Ok(Interpreter {
+			scripts: scenario.iter().map(FlatScript::new).collect(),
+			variables: HashMap::new(),
+			frame: Frame {
+				script: start,
+				pc: 0,
+			},
+			call_stack: vec![],
+			speaker: None,
+			pending_choice: None,
+			tip_level: u8::MAX,
+			skipped_opcodes: HashSet::new(),
+		})
+	}
+
+	/// Only show custom tips up to this level. All tips are shown by default.
+	pub fn with_tip_level(mut self, tip_level: u8) -> Self {
+		self.tip_level = tip_level;
+		self
+	}
+
+	pub fn variable(&self, index: u16) -> i32 {
+		self.variables.get(&index).copied().unwrap_or_default()
+	}
+
+	/// Where the interpreter currently is, as a script number and an address in that script.
+	pub fn position(&self) -> (usize, Option<u32>) {
+		let address = self.scripts[self.frame.script]
+			.opcodes
+			.get(self.frame.pc)
+			.map(|it| it.address());
+		(self.frame.script, address)
+	}
+
+	/// Runs until the next choice or the end of the route, passing each line of text to `on_line`.
+	pub fn run(&mut self, mut on_line: impl FnMut(Line)) -> Result<Stop> {
+		if let Some(choice) = &self.pending_choice {
+			return Err(anyhow!(
+				"Waiting on a choice between {} options, call choose first.",
+				choice.len()
+			));
+		}
+
+		for _ in 0..MAX_STEPS {
+			if let Some(stop) = self.step(&mut on_line)? {
+				return Ok(stop);
+			}
+		}
+
+		Ok(Stop::StepLimit)
+	}
+
+	/// Picks option `index` of the choice the interpreter stopped at.
+	pub fn choose(&mut self, index: usize) -> Result<()> {
+		let targets = self
+			.pending_choice
+			.take()
+			.ok_or_else(|| anyhow!("Not waiting on a choice."))?;
+
+		match targets.get(index) {
+			// A choice without a jump target just carries on after the choice opcode.
+			Some(0) => Ok(()),
+			Some(&target) => self.jump(target),
+			None => {
+				let n_choices = targets.len();
+				self.pending_choice = Some(targets);
+				Err(anyhow!(
+					"Choice {index} is out of range, there are only {n_choices} options."
+				))
+			}
+		}
+	}
+
+	fn step(&mut self, on_line: &mut impl FnMut(Line)) -> Result<Option<Stop>> {
+		let script = &self.scripts[self.frame.script];
+		let Some(&opcode) = script.opcodes.get(self.frame.pc) else {
+			// Running off the end of a script is treated like a return.
+			return Ok(self.ret());
+		};
+		self.frame.pc += 1;
+
+		match opcode {
+			Opcode::OP_DIRECT_JUMP(op) | Opcode::OP_03_DIRECT_JUMP_PHANTOM(op) => {
+				self.jump(op.jump_address)?;
+			}
+			Opcode::JE(op)
+			| Opcode::JNE(op)
+			| Opcode::JG(op)
+			| Opcode::JGE(op)
+			| Opcode::JL(op)
+			| Opcode::JLE(op) => {
+				let lhs = self.variable(op.arg1);
+				let rhs = op.arg2 as i32;
+				let taken = match opcode {
+					Opcode::JE(_) => lhs == rhs,
+					Opcode::JNE(_) => lhs != rhs,
+					Opcode::JG(_) => lhs > rhs,
+					Opcode::JGE(_) => lhs >= rhs,
+					Opcode::JL(_) => lhs < rhs,
+					_ => lhs <= rhs,
+				};
+				if taken {
+					self.jump(op.jump_address)?;
+				}
+			}
+			Opcode::JZ(op) | Opcode::JNZ(op) => {
+				let is_zero = self.variable(op.arg1) == 0;
+				if is_zero == matches!(opcode, Opcode::JZ(_)) {
+					self.jump(op.jump_address)?;
+				}
+			}
+			Opcode::Switch(op) => {
+				let value = self.variable(op.comparison_value);
+				if let Some(arm) = op.arms.iter().find(|arm| arm.index as i32 == value) {
+					self.jump(arm.jump_address)?;
+				}
+			}
+			Opcode::OP_10(op)
+			| Opcode::OP_11(op)
+			| Opcode::OP_12(op)
+			| Opcode::OP_13(op)
+			| Opcode::OP_14(op)
+			| Opcode::OP_15(op)
+			| Opcode::OP_16(op)
+			| Opcode::OP_17(op) => {
+				let value = op.arg2 as i32;
+				let variable = self.variables.entry(op.arg1).or_default();
+				*variable = match op.opcode {
+					0x10 => value,
+					0x11 => variable.wrapping_add(value),
+					0x12 => variable.wrapping_sub(value),
+					0x13 => variable.wrapping_mul(value),
+					0x14 => variable.checked_div(value).unwrap_or_default(),
+					0x15 => variable.checked_rem(value).unwrap_or_default(),
+					0x16 => *variable & value,
+					_ => *variable | value,
+				};
+			}
+			Opcode::OP_JUMP_TO_SCRIPT(op)
+			| Opcode::OP_04_JUMP_TO_SCRIPT_WITH_OFFSET_PHANTOM(op) => {
+				let target = op.target_script as usize;
+				let Some(target_script) = self.scripts.get(target) else {
+					return Err(anyhow!(
+						"Script jump at 0x{:08X} in script {} targets script {target}, which doesn't exist.",
+						op.address,
+						self.frame.script
+					));
+				};
+				let pc = target_script
+					.addresses
+					.get(&(op.jump_address as u32))
+					.copied()
+					.unwrap_or_default();
+
+				if self.call_stack.len() >= MAX_CALL_DEPTH {
+					log::warn!(
+						"Script calls are nested too deeply, treating them as jumps from now on."
+					);
+					self.call_stack.clear();
+				}
+				self.call_stack.push(self.frame);
+				self.frame = Frame { script: target, pc };
+			}
+			Opcode::OP_SCRIPT_RETURN(_) => return Ok(self.ret()),
+			Opcode::OP_FREE_TEXT_OR_CHARNAME(op) if op.opt_arg2.is_none() => {
+				self.speaker = Some(display_text(&op.unicode, &op.translation));
+			}
+			Opcode::OP_46_LP_CHARNAME(op) | Opcode::OP_90_PHANTOM_CHARNAME(op) => {
+				self.speaker = Some(display_text(&op.unicode, &op.translation));
+			}
+			Opcode::OP_FREE_TEXT_OR_CHARNAME(op) => {
+				self.emit(
+					op.address,
+					display_text(&op.unicode, &op.translation),
+					on_line,
+				);
+			}
+			Opcode::OP_47_TEXT(op) => {
+				self.emit(
+					op.address,
+					display_text(&op.unicode, &op.translation),
+					on_line,
+				);
+			}
+			Opcode::OP_TEXTBOX_DISPLAY(op) | Opcode::OP_SPECIAL_TEXT(op) => {
+				self.emit(
+					op.address,
+					display_text(&op.unicode, &op.translation),
+					on_line,
+				);
+			}
+			Opcode::OP_CHOICE(op) | Opcode::OP_MENU_CHOICE(op) => {
+				self.pending_choice = Some(op.choices.iter().map(|it| it.jump_address).collect());
+				return Ok(Some(Stop::Choice(
+					op.choices
+						.iter()
+						.map(|it| display_text(&it.unicode, &it.translation))
+						.collect(),
+				)));
+			}
+			Opcode::OP_CUSTOM_TIP_77(op) => {
+				if op.condition > self.tip_level {
+					self.frame.pc += op.skip as usize;
+				}
+			}
+			Opcode::Tip(op) => {
+				if op.level <= self.tip_level {
+					self.emit(op.address, unescape_str(&op.text), on_line);
+				}
+			}
+			Opcode::OP_RawBytes(op) => {
+				log::warn!(
+					"Running over 0x{:X} undecoded bytes at 0x{:08X} in script {}.",
+					op.bytes.len(),
+					op.address,
+					self.frame.script
+				);
+			}
+			_ => {
+				if self.skipped_opcodes.insert(opcode.opcode()) {
+					log::debug!(
+						"Skipping opcode 0x{:02X}, it has no effect in the interpreter.",
+						opcode.opcode()
+					);
+				}
+			}
+		}
+
+		Ok(None)
+	}
+
+	fn emit(&mut self, address: u32, text: String, on_line: &mut impl FnMut(Line)) {
+		on_line(Line {
+			script: self.frame.script,
+			address,
+			speaker: self.speaker.take(),
+			text,
+		});
+	}
+
+	fn jump(&mut self, address: u32) -> Result<()> {
+		let script = &self.scripts[self.frame.script];
+		self.frame.pc = *script.addresses.get(&address).ok_or_else(|| {
+			anyhow!(
+				"Jump target 0x{address:08X} doesn't exist in script {}.",
+				self.frame.script
+			)
+		})?;
+		Ok(())
+	}
+
+	fn ret(&mut self) -> Option<Stop> {
+		match self.call_stack.pop() {
+			Some(frame) => {
+				self.frame = frame;
+				None
+			}
+			None => Some(Stop::End),
+		}
+	}
+}
+
+/// Runs a route without any interaction, taking the options in `choices` in order whenever the
+/// script asks for one, and returns the transcript of everything shown on screen.
+///
+/// Each line of text becomes one transcript line, with line breaks escaped the same way as in
+/// translation documents. Picked options are recorded as `> [index] text`.
+pub fn run_route(
+	scenario: &[Script],
+	start: usize,
+	choices: &[usize],
+	tip_level: u8,
+) -> Result<Vec<String>> {
+	let mut interpreter = Interpreter::new(scenario, start)?.with_tip_level(tip_level);
+	let mut choices = choices.iter().copied();
+	let mut transcript = vec![];
+
+	loop {
+		let stop = interpreter.run(|line| {
+			transcript.push(match line.speaker {
+				Some(speaker) => format!("{}: {}", escape_str(&speaker), escape_str(&line.text)),
+				None => escape_str(&line.text),
+			})
+		})?;
+
+		match stop {
+			Stop::Choice(options) => {
+				let (script, address) = interpreter.position();
+				let choice = choices.next().ok_or_else(|| {
+					anyhow!("Ran out of choices at a choice in script {script} at {address:08X?}.")
+				})?;
+				let text = options
+					.get(choice)
+					.map(|it| escape_str(it))
+					.unwrap_or_default();
+				interpreter.choose(choice)?;
+				transcript.push(format!("> [{choice}] {text}"));
+			}
+			Stop::End => break,
+			Stop::StepLimit => {
+				let (script, address) = interpreter.position();
+				return Err(anyhow!(
+					"Gave up after too many steps in script {script} at {address:08X?}, this is probably an infinite loop."
+				));
+			}
+		}
+	}
+
+	let unused = choices.count();
+	if unused != 0 {
+		log::warn!("The route ended with {unused} choices left over.");
+	}
+
+	Ok(transcript)
+}
+
+/// Parses a choice sequence, a list of option indices separated by commas or whitespace.
+/// Everything after a `#` on a line is ignored.
+pub fn parse_choice_sequence(input: &str) -> Result<Vec<usize>> {
+	input
+		.lines()
+		.map(|line| line.split('#').next().unwrap_or_default())
+		.flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+		.filter(|it| !it.is_empty())
+		.map(|it| {
+			it.parse()
+				.map_err(|_| anyhow!("\"{it}\" is not a valid choice index."))
+		})
+		.collect()
+}
+
+fn display_text(unicode: &str, translation: &Option<String>) -> String {
+	unescape_str(translation.as_deref().unwrap_or(unicode))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{parse_choice_sequence, run_route, Interpreter, Stop};
+	use crate::opcodescript::{Opcode, Quirks, Script, TipOpcode};
+
+	#[test]
+	fn test_conditional_jump() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x10, 0x01, 0x00, 0x05, 0x00, // var 1 = 5
+			0x06, 0x01, 0x00, 0x05, 0x00, 0x19, 0x00, 0x00, 0x00, // je var 1, 5, 0x19
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00, // skipped
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00,
+			0x05, 0x00, // return
+		];
+
+		let (script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		let scenario = [script];
+		let mut interpreter = Interpreter::new(&scenario, 0).unwrap();
+		let mut lines = vec![];
+		let stop = interpreter.run(|line| lines.push(line.text)).unwrap();
+
+		assert_eq!(stop, Stop::End);
+		assert_eq!(lines, vec!["B"]);
+		assert_eq!(interpreter.variable(1), 5);
+	}
+
+	#[test]
+	fn test_jump_to_tipped_line() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x01, 0x0C, 0x00, 0x00, 0x00, // jmp 0x0C
+			0x4A, 0xFF, 0xFF, // wait
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00, // 0x0C
+			0x4A, 0xFF, 0xFF, // wait
+			0x05, 0x00, // return
+		];
+
+		let (mut script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+		// Where a tip for the line ends up, after its wait.
+		script.opcodes.insert(
+			4,
+			Opcode::Tip(TipOpcode {
+				address: 0x0C,
+				actual_address: 0,
+				level: 1,
+				text: "T".to_string(),
+			}),
+		);
+
+		let scenario = [script];
+		let mut interpreter = Interpreter::new(&scenario, 0).unwrap();
+		let mut lines = vec![];
+		assert_eq!(
+			interpreter.run(|line| lines.push(line.text)).unwrap(),
+			Stop::End
+		);
+		assert_eq!(lines, vec!["A", "T"]);
+	}
+
+	#[test]
+	fn test_route() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x31, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, // choice with 2 options
+			0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x23, 0x00, 0x00, 0x00, b'X', 0x00,
+			0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x2F, 0x00, 0x00, 0x00, b'Y', 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00, // 0x23
+			0x01, 0x36, 0x00, 0x00, 0x00, // jmp 0x36
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00, // 0x2F
+			0x05, 0x00, // 0x36
+		];
+
+		let (script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+		let scenario = [script];
+
+		let choices = parse_choice_sequence("1 # take the second option\n").unwrap();
+		assert_eq!(
+			run_route(&scenario, 0, &choices, u8::MAX).unwrap(),
+			vec!["> [1] Y", "B"]
+		);
+		assert_eq!(
+			run_route(&scenario, 0, &[0], u8::MAX).unwrap(),
+			vec!["> [0] X", "A"]
+		);
+		assert!(run_route(&scenario, 0, &[], u8::MAX).is_err());
+		assert_eq!(
+			parse_choice_sequence("0,1, 1\n0").unwrap(),
+			vec![0, 1, 1, 0]
+		);
+	}
+}
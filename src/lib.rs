@@ -1,12 +1,23 @@
+use crate::commands::PackOutput;
+use crate::config::ProjectConfig;
 use crate::opcodescript::Quirks;
 use camino::Utf8PathBuf;
 
+pub mod bps;
 pub mod commands;
+pub mod config;
+pub mod interpreter;
 pub mod logging;
 pub mod lz77;
 pub mod opcodescript;
+pub mod pack_cache;
+pub mod port;
+pub mod reachability;
+pub mod scenario_diff;
 pub mod scenario_pack;
 pub mod util;
+pub mod voice;
+pub mod xref;
 
 pub fn main_preamble(typ: &str) -> (Vec<Utf8PathBuf>, String) {
 	use walkdir;
@@ -31,6 +42,32 @@ pub fn main_preamble(typ: &str) -> (Vec<Utf8PathBuf>, String) {
 		args.remove(idx);
 	}
 
+	let config = if let Some(path) = flag_value("config") {
+		ProjectConfig::load(&Utf8PathBuf::from(path)).map(Some)
+	} else {
+		ProjectConfig::discover(&util::current_dir())
+	};
+	match config {
+		Ok(config) => {
+			let mut config = config.unwrap_or_default();
+			if args.iter().any(|it| it == "--recover") {
+				config.recover = Some(true);
+			}
+			config::init(config)
+		}
+		Err(e) => {
+			log::error!("{e:#}");
+			std::process::exit(1);
+		}
+	}
+
+	if quirks_string.is_empty() {
+		quirks_string = config::get().quirks.clone().unwrap_or_default();
+	}
+
+	// Anything else that looks like a flag is handled by the binary itself.
+	args.retain(|it| !it.starts_with("--"));
+
 	let mut out_files: Vec<Utf8PathBuf> = vec![];
 	for arg in args {
 		let walker = walkdir::WalkDir::new(&arg);
@@ -50,6 +87,120 @@ pub fn main_preamble(typ: &str) -> (Vec<Utf8PathBuf>, String) {
 	(out_files, quirks_string)
 }
 
+/// Paths and settings shared by the tools that repack a scenario.
+pub struct PackOptions {
+	pub yaml_dir: Utf8PathBuf,
+	pub text_dir: Utf8PathBuf,
+	pub output: Utf8PathBuf,
+	pub output_mode: PackOutput,
+}
+
+/// Works out where to pack from and to, given the text files found by [`main_preamble`].
+///
+/// Command line flags (`--yaml-dir=`, `--text-dir=`, `--output=`, `--no-compress`,
+/// `--loose-scripts`) win over the positional text directory, which wins over the project
+/// configuration.
+pub fn pack_options(files: &[Utf8PathBuf]) -> PackOptions {
+	let config = config::get();
+
+	let positional_dir = files
+		.first()
+		.and_then(|it| it.parent())
+		.map(ToOwned::to_owned)
+		.or_else(|| {
+			std::env::args()
+				.skip(1)
+				.find(|it| !it.starts_with('-'))
+				.map(Utf8PathBuf::from)
+		});
+
+	let Some(text_dir) = flag_value("text-dir")
+		.map(Utf8PathBuf::from)
+		.or_else(|| positional_dir.clone())
+		.or_else(|| config.text_dir.clone())
+	else {
+		log::error!("No script text directory given, either as an argument or in yeti.toml.");
+		std::process::exit(1);
+	};
+
+	let Some(yaml_dir) = flag_value("yaml-dir")
+		.map(Utf8PathBuf::from)
+		.or_else(|| {
+			let dir = positional_dir.as_ref()?;
+			let name = dir.file_name()?.replace(".script", ".yaml");
+			Some(dir.parent()?.join(name))
+		})
+		.or_else(|| config.yaml_dir.clone())
+	else {
+		log::error!(
+			"No yaml directory configured. Pass --yaml-dir=, set yaml_dir in yeti.toml, or give a \
+			 text directory next to its yaml directory."
+		);
+		std::process::exit(1);
+	};
+
+	let (output, output_mode) = pack_output();
+
+	PackOptions {
+		yaml_dir,
+		text_dir,
+		output,
+		output_mode,
+	}
+}
+
+/// Works out where a packed scenario goes, and in what form.
+///
+/// With `--loose-scripts`, the scripts are written to a directory instead, which defaults to the
+/// scenario file's path with `.opcodescript` appended. An output path ending in `/` also asks for
+/// loose scripts, the way it did before the flag existed.
+pub fn pack_output() -> (Utf8PathBuf, PackOutput) {
+	let config = config::get();
+
+	let output = flag_value("output").map(Utf8PathBuf::from);
+	// An output path ending in a slash used to be how loose scripts were asked for, and they go
+	// straight into that directory.
+	if let Some(output) = output
+		.clone()
+		.or_else(|| config.output.clone())
+		.filter(|it| it.as_str().ends_with('/'))
+	{
+		log::warn!(
+			"Writing loose scripts to {output} because it ends in '/'. Pass --loose-scripts to ask \
+			 for them explicitly, this will stop working in a future release."
+		);
+		return (output, PackOutput::LooseScripts);
+	}
+
+	if std::env::args().any(|it| it == "--loose-scripts") {
+		let output = output.unwrap_or_else(|| {
+			let scenario = config
+				.output
+				.clone()
+				.unwrap_or_else(|| util::current_dir().join("sn.bin"));
+			Utf8PathBuf::from(format!("{scenario}.opcodescript"))
+		});
+		return (output, PackOutput::LooseScripts);
+	}
+
+	let output = output
+		.or_else(|| config.output.clone())
+		.unwrap_or_else(|| util::current_dir().join("sn.bin"));
+	let compress =
+		!std::env::args().any(|it| it == "--no-compress") && config.compress.unwrap_or(true);
+
+	(output, PackOutput::Scenario { compress })
+}
+
+/// Returns the value of a `--name=value` flag, if it was passed.
+pub fn flag_value(name: &str) -> Option<String> {
+	let prefix = format!("--{name}=");
+	std::env::args().find_map(|it| {
+		it.strip_prefix(&prefix)
+			.map(|it| it.trim_matches('"').to_string())
+	})
+}
+
 pub fn parse_quirks_arg(quirks_arg: &str) -> Quirks {
 	let quirks_list = quirks_arg.split(",").collect::<Vec<_>>();
 
@@ -2,16 +2,42 @@ use crate::util::{transmute_to_u16, transmute_to_u32};
 
 // A very dumb compression implementation fo LZ77 that will actually *increase* file size, not decrease it.
 pub(crate) fn lz77_compress(input: &[u8]) -> Vec<u8> {
-	(input.len() as u32)
-		.to_le_bytes()
-		.into_iter()
-		.chain(
-			input
-				.chunks(8)
-				.flat_map(|chunk| [0xFF].iter().chain(chunk))
-				.copied(),
-		)
-		.collect()
+	let mut writer = Lz77Writer::new(input.len());
+	writer.write(input);
+	writer.finish()
+}
+
+/// Compresses data piece by piece, the same way as [`lz77_compress`], so it doesn't have to be
+/// gathered into one buffer first. The total size has to be known up front, since it goes in
+/// the header.
+pub(crate) struct Lz77Writer {
+	output: Vec<u8>,
+	/// How many bytes have been written since the last flag byte.
+	group: usize,
+}
+
+impl Lz77Writer {
+	pub(crate) fn new(size: usize) -> Self {
+		let mut output = Vec::with_capacity(4 + size + size.div_ceil(8));
+		output.extend((size as u32).to_le_bytes());
+		Self { output, group: 0 }
+	}
+
+	pub(crate) fn write(&mut self, mut data: &[u8]) {
+		while !data.is_empty() {
+			if self.group == 0 {
+				self.output.push(0xFF);
+			}
+			let length = data.len().min(8 - self.group);
+			self.output.extend(&data[..length]);
+			self.group = (self.group + length) % 8;
+			data = &data[length..];
+		}
+	}
+
+	pub(crate) fn finish(self) -> Vec<u8> {
+		self.output
+	}
 }
 
 // This is a 1:1 translation of the python code from https://github.com/mchubby/yetireg_tools/tree/master/splz77/splz77_decompress.py
@@ -68,3 +94,23 @@ pub(crate) fn lz77_decompress(input: &[u8]) -> Vec<u8> {
 
 	output
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{lz77_compress, lz77_decompress, Lz77Writer};
+
+	#[test]
+	fn test_lz77_writer() {
+		let data: Vec<u8> = (0..100).collect();
+
+		let mut writer = Lz77Writer::new(data.len());
+		for chunk in [&data[..3], &data[3..3], &data[3..20], &data[20..]] {
+			writer.write(chunk);
+		}
+		let compressed = writer.finish();
+
+		assert_eq!(compressed, lz77_compress(&data));
+		assert_eq!(compressed.len(), 4 + 100 + 13);
+		assert_eq!(lz77_decompress(&compressed), data);
+	}
+}
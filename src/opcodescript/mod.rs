@@ -5,16 +5,16 @@
 use std::collections::{HashMap, HashSet};
 
 use anyhow::{anyhow, Error, Result};
-use opcodes::{BinarySerialize, Custom77, InsertOpcode};
-use rayon::prelude::{IndexedParallelIterator, IntoParallelRefIterator};
 use serde::{Deserialize, Serialize};
 
 mod opcode_impl;
 mod opcodes;
+mod view;
 
 use crate::util::OkWrappable;
 pub use opcode_impl::Quirks;
 pub use opcodes::*;
+pub use view::{OpcodeIter, OpcodeView};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Header {
@@ -35,8 +35,70 @@ pub struct Script {
 	pub footer: Footer,
 }
 
+/// A stretch of a script that recovery mode couldn't disassemble and kept as raw bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveredRegion {
+	pub address: u32,
+	pub size: usize,
+}
+
+/// A problem that would stop a script from being serialized correctly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+	/// A jump, switch arm or choice whose target doesn't match the address of any opcode.
+	DanglingTarget {
+		/// Address of the opcode doing the jumping.
+		source: u32,
+		target: u32,
+		/// What kind of jump it is, for the error message.
+		kind: String,
+	},
+	/// More than one opcode has this address, so jumps to it are ambiguous.
+	DuplicateAddress { address: u32, count: usize },
+}
+
+impl std::fmt::Display for ValidationError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ValidationError::DanglingTarget {
+				source,
+				target,
+				kind,
+			} => write!(
+				f,
+				"{kind} at 0x{source:08X} jumps to 0x{target:08X}, but no opcode has that address."
+			),
+			ValidationError::DuplicateAddress { address, count } => write!(
+				f,
+				"{count} opcodes have the address 0x{address:08X}, so jumps to it are ambiguous."
+			),
+		}
+	}
+}
+
 impl Script {
 	pub fn new(data: &[u8], quirks: Quirks) -> Result<(Self, Option<Error>)> {
+		let (script, error, _) = Self::disassemble(data, quirks, false)?;
+		(script, error).wrap_ok()
+	}
+
+	/// Like [`Script::new`], but instead of giving up at the first opcode that can't be decoded,
+	/// keeps the undecodable bytes as an [`Opcode::OP_RawBytes`] and carries on from the next
+	/// place that looks like a text opcode.
+	///
+	/// The returned error is the first one encountered, even if disassembly recovered from it.
+	pub fn new_recovering(
+		data: &[u8],
+		quirks: Quirks,
+	) -> Result<(Self, Option<Error>, Vec<RecoveredRegion>)> {
+		Self::disassemble(data, quirks, true)
+	}
+
+	fn disassemble(
+		data: &[u8],
+		quirks: Quirks,
+		recover: bool,
+	) -> Result<(Self, Option<Error>, Vec<RecoveredRegion>)> {
 		let start = crate::util::transmute_to_u32(0, &data)? as usize;
 
 		let mut address = start;
@@ -50,6 +112,7 @@ impl Script {
 		let mut idx = 0;
 		let mut at_end = false;
 		let mut encountered_error: Option<anyhow::Error> = None;
+		let mut recovered_regions = vec![];
 
 		let mut skip_stack: Vec<(Custom77, usize)> = vec![];
 		let mut marked_indices = HashSet::new();
@@ -132,8 +195,32 @@ impl Script {
 					}
 				}
 				Err(e) => {
-					encountered_error = Some(e);
-					break;
+					log::debug!("Could not decode opcode at 0x{address:08X}: {e}");
+					encountered_error.get_or_insert(e);
+					if !recover {
+						break;
+					}
+
+					let Some(resync_address) = find_resync_point(address + 1, data, quirks) else {
+						break;
+					};
+
+					log::warn!(
+						"Kept 0x{:X} undecodable bytes at 0x{address:08X} as raw data, resuming at 0x{resync_address:08X}. Starts with: {:02X?}",
+						resync_address - address,
+						&data[address..resync_address.min(address + 16)]
+					);
+					recovered_regions.push(RecoveredRegion {
+						address: address as u32,
+						size: resync_address - address,
+					});
+					opcodes.push(Opcode::OP_RawBytes(RawBytesOpcode {
+						address: address as u32,
+						actual_address: address as u32,
+						bytes: data[address..resync_address].to_owned(),
+					}));
+					idx += 1;
+					address = resync_address;
 				}
 			}
 		}
@@ -153,219 +240,315 @@ impl Script {
 			opcodes,
 		};
 
-		(script, encountered_error).wrap_ok()
+		(script, encountered_error, recovered_regions).wrap_ok()
 	}
 
-	pub fn binary_serialize(&self) -> Vec<u8> {
-		let mut output = vec![];
-		let mut opcodes = self.opcodes.clone();
-		output.extend(&self.header.bytes);
-
-		// get jump addresses for everything first.
-		let mut jump_map: HashMap<u32, HashMap<u16, usize>> = HashMap::new();
-		let mut actual_address = self
-			.opcodes
-			.first()
-			.map(Opcode::address)
-			.unwrap_or_default() as usize;
-		log::debug!("Actual address start is 0x{actual_address:08X}");
-		for opcode in opcodes.iter_mut() {
-			match opcode {
-				Opcode::OP_DIRECT_JUMP(op) | Opcode::OP_03_DIRECT_JUMP_PHANTOM(op) => {
-					let mut map = HashMap::new();
-					let idx = self
-						.opcodes
-						.par_iter()
-						.position_any(|it| it.address() == op.jump_address)
-						.ok_or_else(|| {
-							anyhow!(
-								"Could not find jump target 0x{:08X} for direct jump at 0x{:08X}",
-								op.jump_address,
-								op.address
-							)
-						})
-						.unwrap();
-					map.insert(0, idx);
-					log::debug!(
-						"Direct jump opcode at 0x{:08X} (actual 0x{:08X}) jumps to: 0x{:04X}",
-						op.address,
-						actual_address,
-						self.opcodes[idx].address(),
-					);
-					jump_map.insert(op.address, map);
+	/// Checks that every jump, switch arm and choice target exists and is unambiguous, reporting
+	/// every problem found rather than just the first. Serializing a script that fails validation
+	/// will panic.
+	pub fn validate(&self) -> Vec<ValidationError> {
+		let mut errors = vec![];
+
+		// Jumps land on the least nested opcode with their address, so an inserted opcode can reuse
+		// an address that a less nested one owns. Opcodes at the same depth claiming the same
+		// address are ambiguous though, whether they're copies in two inserts or at the top level.
+		let mut owned: HashSet<u32> = HashSet::new();
+		let mut duplicates = vec![];
+		for level in nesting_levels(&self.opcodes) {
+			let mut address_counts: HashMap<u32, usize> = HashMap::new();
+			for address in level.iter().flat_map(|it| it.jump_addresses()) {
+				if !owned.contains(&address) {
+					*address_counts.entry(address).or_default() += 1;
 				}
-				Opcode::JNE(op)
-				| Opcode::JE(op)
-				| Opcode::JLE(op)
-				| Opcode::JL(op)
-				| Opcode::JGE(op)
-				| Opcode::JG(op) => {
-					let mut map = HashMap::new();
-					let thing = self
-						.opcodes
-						.par_iter()
-						.position_any(|it| it.address() == op.jump_address)
-						.ok_or_else(|| {
-							anyhow!(
-								"Could not find jump target 0x{:08X} for conditional jump at 0x{:08X}",
-								op.jump_address,
-								op.address
-							)
-						})
-						.unwrap();
-					map.insert(0, thing);
-					jump_map.insert(op.address, map);
-					log::debug!(
-						"Conditional jump Opcode at 0x{:08X} (actual {:08X}) jumps to: {:08X}",
-						op.address,
-						actual_address,
-						self.opcodes[thing].address()
-					);
-				}
-				Opcode::JZ(op) | Opcode::JNZ(op) => {
-					let mut map = HashMap::new();
-					let data = self
-						.opcodes
-						.par_iter()
-						.position_any(|it| it.address() == op.jump_address)
-						.ok_or_else(|| {
-							anyhow!(
-								"Could not find jump target 0x{:08X} for Z/NZ jump at 0x{:08X}",
-								op.jump_address,
-								op.address
-							)
-						})
-						.unwrap();
-					map.insert(0, data);
-					jump_map.insert(op.address, map);
-					log::debug!(
-						"Conditional jump Opcode at 0x{:08X} (actual {:08X}) jumps to: {:08X}",
-						op.address,
-						actual_address,
-						self.opcodes[data].address(),
-					);
-				}
-				Opcode::Switch(op) => {
-					let jumps = op
+			}
+			duplicates.extend(
+				address_counts
+					.iter()
+					.filter(|(_, &count)| count > 1)
+					.map(|(&address, &count)| ValidationError::DuplicateAddress { address, count }),
+			);
+			owned.extend(address_counts.into_keys());
+		}
+		duplicates.sort_by_key(|it| match it {
+			ValidationError::DuplicateAddress { address, .. } => *address,
+			_ => 0,
+		});
+		errors.extend(duplicates);
+
+		fn check_targets(
+			opcodes: &[Opcode],
+			known: &HashSet<u32>,
+			errors: &mut Vec<ValidationError>,
+		) {
+			for opcode in opcodes {
+				let targets: Vec<(u32, String)> = match opcode {
+					Opcode::OP_DIRECT_JUMP(op) | Opcode::OP_03_DIRECT_JUMP_PHANTOM(op) => {
+						vec![(op.jump_address, "Direct jump".to_string())]
+					}
+					Opcode::JNE(op)
+					| Opcode::JE(op)
+					| Opcode::JLE(op)
+					| Opcode::JL(op)
+					| Opcode::JGE(op)
+					| Opcode::JG(op) => vec![(op.jump_address, "Conditional jump".to_string())],
+					Opcode::JZ(op) | Opcode::JNZ(op) => {
+						vec![(op.jump_address, "Z/NZ jump".to_string())]
+					}
+					Opcode::Switch(op) => op
 						.arms
 						.iter()
-						.map(|arm| {
-							(
-								arm.index,
-								self.opcodes
-									.par_iter()
-									.position_any(|it| it.address() == arm.jump_address)
-									.ok_or_else(|| {
-										anyhow!(
-											"Could not find jump target 0x{:08X} for switch arm at 0x{:08X}",
-											arm.jump_address,
-											op.address
-										)
-									})
-									.unwrap(),
-							)
-						})
-						.collect();
-					jump_map.insert(op.address, jumps);
-				}
-				Opcode::OP_CHOICE(op) | Opcode::OP_MENU_CHOICE(op) => {
-					let jumps = op
+						.map(|arm| (arm.jump_address, format!("Switch arm {}", arm.index)))
+						.collect(),
+					Opcode::OP_CHOICE(op) | Opcode::OP_MENU_CHOICE(op) => op
 						.choices
 						.iter()
 						.enumerate()
-						.filter_map(|(idx, choice)| {
-							if choice.jump_address == 0 {
-								None
-							} else {
-								Some((
-									idx as u16,
-									self.opcodes
-										.par_iter()
-										.position_any(|it| it.address() == choice.jump_address)
-										.ok_or_else(|| {
-											anyhow!(
-												"Could not find jump target 0x{:08X} for choice {} at 0x{:08X}",
-												choice.jump_address,
-												idx,
-												op.address
-											)
-										})
-										.unwrap(),
-								))
-							}
-						})
-						.collect();
-					jump_map.insert(op.address, jumps);
+						.filter(|(_, choice)| choice.jump_address != 0)
+						.map(|(idx, choice)| (choice.jump_address, format!("Choice {idx}")))
+						.collect(),
+					Opcode::OP_Insert(insert) => {
+						check_targets(&insert.contents, known, errors);
+						vec![]
+					}
+					Opcode::OP_Replace(replace) => {
+						check_targets(&replace.replacement, known, errors);
+						vec![]
+					}
+					_ => vec![],
+				};
+
+				for (target, kind) in targets {
+					if !known.contains(&target) {
+						errors.push(ValidationError::DanglingTarget {
+							source: opcode.address(),
+							target,
+							kind,
+						});
+					}
 				}
-				_ => {}
 			}
-			opcode.set_actual_address(actual_address);
-			actual_address += opcode.size();
 		}
+		let known: HashSet<u32> = addressable(&self.opcodes)
+			.into_iter()
+			.map(|(address, _)| address)
+			.collect();
+		check_targets(&self.opcodes, &known, &mut errors);
 
-		for opcode in opcodes.iter().cloned() {
-			let opcode = match adjust_single_opcode(opcode, &jump_map, &opcodes) {
-				Some(value) => value,
-				None => continue, // means we've got a bad opcode.
-			};
+		errors
+	}
 
-			let serialized = match &opcode {
-				Opcode::OP_Insert(insert) => {
-					let mut contents = Vec::new();
-					for (idx, opcode) in insert.contents.iter().enumerate() {
-						if let Opcode::OP_CUSTOM_TIP_77(custom) = opcode {
-							let mut serialized = custom.binary_serialize();
-							let mut offset: u16 = 4;
-							for i in 1..(custom.skip + 1) {
-								let curr_opcode =
-									insert.contents.get(idx + i as usize).cloned().unwrap();
-								let curr_offset = curr_opcode.size();
-								offset += curr_offset as u16;
-							}
+	pub fn binary_serialize(&self) -> Vec<u8> {
+		let jump_table = self.jump_table();
 
-							log::info!(
-                "Encoding skip of 0x{offset:04X} bytes; equivalent to {} instructions.",
-                custom.skip
-              );
-
-							let offset_bytes = offset.to_le_bytes();
-							serialized[2..].copy_from_slice(&offset_bytes);
-
-							log::info!(
-								"Generated tip opcode: {:02X} {:02X} {:02X} {:02X}",
-								serialized[0],
-								serialized[1],
-								serialized[2],
-								serialized[3]
-							);
-							contents.extend(serialized);
-						} else {
-							contents.extend(opcode.binary_serialize());
-						}
-					}
-					contents
-				}
+		let size = self.header.bytes.len()
+			+ self.opcodes.iter().map(Opcode::size).sum::<usize>()
+			+ self.footer.bytes.len();
+		let mut output = Vec::with_capacity(size);
+		output.extend(&self.header.bytes);
+
+		for opcode in &self.opcodes {
+			// Only opcodes with jump targets need changing, so only those are copied.
+			let adjusted;
+			let opcode = if has_jump_targets(opcode) {
+				adjusted = match adjust_single_opcode(opcode.clone(), &jump_table) {
+					Some(value) => value,
+					None => continue, // means we've got a bad opcode.
+				};
+				&adjusted
+			} else {
+				opcode
+			};
+
+			let serialized = match opcode {
+				Opcode::OP_Insert(insert) => serialize_inserted(&insert.contents),
+				Opcode::OP_Replace(replace) => serialize_inserted(&replace.replacement),
 				_ => opcode.binary_serialize(),
 			};
 
-			actual_address += serialized.len();
 			output.extend(serialized);
 		}
 		output.extend(&self.footer.bytes);
 
 		output
 	}
+
+	/// Lays the script out, and maps every address a jump can use to where the opcode it leads to
+	/// ends up. When several opcodes share an address, the one least deeply nested in inserts and
+	/// replacements wins, the same as in [`addressable`].
+	fn jump_table(&self) -> HashMap<u32, u32> {
+		let start = self
+			.opcodes
+			.first()
+			.map(Opcode::address)
+			.unwrap_or_default();
+		log::debug!("Actual address start is 0x{start:08X}");
+
+		let mut table = HashMap::with_capacity(self.opcodes.len());
+		lay_out(&self.opcodes, start, 0, &mut table);
+		table
+			.into_iter()
+			.map(|(address, (_, actual_address))| (address, actual_address))
+			.collect()
+	}
 }
 
-fn adjust_single_opcode(
-	opcode: Opcode,
-	jump_table: &HashMap<u32, HashMap<u16, usize>>,
+/// Records where `opcodes` end up when laid out from `actual_address`, along with how deeply
+/// nested they are, keeping the least nested opcode for each address.
+fn lay_out(
 	opcodes: &[Opcode],
-) -> Option<Opcode> {
+	mut actual_address: u32,
+	depth: usize,
+	table: &mut HashMap<u32, (usize, u32)>,
+) {
+	for opcode in opcodes {
+		for address in opcode.jump_addresses() {
+			let entry = table.entry(address).or_insert((depth, actual_address));
+			if depth < entry.0 {
+				*entry = (depth, actual_address);
+			}
+		}
+		match opcode {
+			Opcode::OP_Insert(insert) => {
+				lay_out(&insert.contents, actual_address, depth + 1, table)
+			}
+			Opcode::OP_Replace(replace) => {
+				lay_out(&replace.replacement, actual_address, depth + 1, table)
+			}
+			_ => {}
+		}
+		actual_address += opcode.size() as u32;
+	}
+}
+
+/// Whether serializing the opcode means pointing some jump at where its target ended up.
+fn has_jump_targets(opcode: &Opcode) -> bool {
+	matches!(
+		opcode,
+		Opcode::OP_DIRECT_JUMP(_)
+			| Opcode::OP_03_DIRECT_JUMP_PHANTOM(_)
+			| Opcode::JNE(_)
+			| Opcode::JE(_)
+			| Opcode::JLE(_)
+			| Opcode::JL(_)
+			| Opcode::JGE(_)
+			| Opcode::JG(_)
+			| Opcode::JNZ(_)
+			| Opcode::JZ(_)
+			| Opcode::Switch(_)
+			| Opcode::OP_CHOICE(_)
+			| Opcode::OP_MENU_CHOICE(_)
+			| Opcode::OP_Insert(_)
+			| Opcode::OP_Replace(_)
+	)
+}
+
+/// Every address a jump can use, along with the opcode it leads to, in order of priority: top level
+/// opcodes first, then the contents of inserts and replacements. That way an inserted opcode that
+/// reuses an address from the original script can't take over jumps meant for the original.
+fn addressable(opcodes: &[Opcode]) -> Vec<(u32, &Opcode)> {
+	nesting_levels(opcodes)
+		.into_iter()
+		.flatten()
+		.flat_map(|opcode| {
+			opcode
+				.jump_addresses()
+				.into_iter()
+				.map(move |it| (it, opcode))
+		})
+		.collect()
+}
+
+/// The opcodes of a script grouped by how deeply they're nested in inserts and replacements, top
+/// level opcodes first.
+fn nesting_levels(opcodes: &[Opcode]) -> Vec<Vec<&Opcode>> {
+	let mut output = vec![];
+	let mut level: Vec<&Opcode> = opcodes.iter().collect();
+	while !level.is_empty() {
+		let mut next = vec![];
+		for opcode in &level {
+			match opcode {
+				Opcode::OP_Insert(insert) => next.extend(&insert.contents),
+				Opcode::OP_Replace(replace) => next.extend(&replace.replacement),
+				_ => {}
+			}
+		}
+		output.push(level);
+		level = next;
+	}
+	output
+}
+
+/// Serializes opcodes added to a script, filling in the skip offsets of any custom tips among them.
+fn serialize_inserted(contents: &[Opcode]) -> Vec<u8> {
+	let mut output = Vec::new();
+	for (idx, opcode) in contents.iter().enumerate() {
+		if let Opcode::OP_CUSTOM_TIP_77(custom) = opcode {
+			let mut serialized = custom.binary_serialize();
+			let mut offset: u16 = 4;
+			for i in 1..(custom.skip + 1) {
+				let curr_opcode = contents.get(idx + i as usize).cloned().unwrap();
+				let curr_offset = curr_opcode.size();
+				offset += curr_offset as u16;
+			}
+
+			log::info!(
+				"Encoding skip of 0x{offset:04X} bytes; equivalent to {} instructions.",
+				custom.skip
+			);
+
+			let offset_bytes = offset.to_le_bytes();
+			serialized[2..].copy_from_slice(&offset_bytes);
+
+			log::info!(
+				"Generated tip opcode: {:02X} {:02X} {:02X} {:02X}",
+				serialized[0],
+				serialized[1],
+				serialized[2],
+				serialized[3]
+			);
+			output.extend(serialized);
+		} else {
+			output.extend(opcode.binary_serialize());
+		}
+	}
+	output
+}
+
+/// Finds the next address from `start` that looks like the beginning of a text opcode, which is
+/// the most reliable place to pick disassembly back up after hitting something unknown.
+///
+/// To count, the text has to decode cleanly and the opcode after it has to decode as well.
+fn find_resync_point(start: usize, data: &[u8], quirks: Quirks) -> Option<usize> {
+	(start..data.len()).find(|&address| {
+		if ![0x45, 0x47].contains(&data[address]) {
+			return false;
+		}
+
+		let Ok(opcode) = Opcode::eat(address, data, quirks) else {
+			return false;
+		};
+		let text = match &opcode {
+			Opcode::OP_TEXTBOX_DISPLAY(op) => &op.unicode,
+			Opcode::OP_FREE_TEXT_OR_CHARNAME(op) => &op.unicode,
+			Opcode::OP_47_TEXT(op) => &op.unicode,
+			_ => return false,
+		};
+		if text.is_empty() || text.contains(char::REPLACEMENT_CHARACTER) {
+			return false;
+		}
+		if text.chars().any(|it| it.is_control()) {
+			return false;
+		}
+
+		let next = address + opcode.size();
+		next < data.len() && Opcode::eat(next, data, quirks).is_ok()
+	})
+}
+
+fn adjust_single_opcode(opcode: Opcode, jump_table: &HashMap<u32, u32>) -> Option<Opcode> {
 	let opcode = match opcode {
 		Opcode::OP_DIRECT_JUMP(mut op) => {
-			let tbl_entry = &jump_table[&op.address];
-			op.jump_address = opcodes[tbl_entry[&0]].actual_address();
+			op.jump_address = resolve_jump(jump_table, op.jump_address, op.address);
 			log::debug!(
 				"Adjusting direct jump Opcode at 0x{:08X} (actual 0x{:08X}) to jump to: 0x{:08X}",
 				op.address,
@@ -375,8 +558,7 @@ fn adjust_single_opcode(
 			Opcode::OP_DIRECT_JUMP(op)
 		}
 		Opcode::OP_03_DIRECT_JUMP_PHANTOM(mut op) => {
-			let tbl_entry = &jump_table[&op.address];
-			op.jump_address = opcodes[tbl_entry[&0]].actual_address();
+			op.jump_address = resolve_jump(jump_table, op.jump_address, op.address);
 			log::debug!(
         "Adjusting direct jump Opcode (03) at 0x{:08X} (actual 0x{:08X}) to jump to: 0x{:08X}",
         op.address,
@@ -391,8 +573,7 @@ fn adjust_single_opcode(
 		| Opcode::JL(mut op)
 		| Opcode::JGE(mut op)
 		| Opcode::JG(mut op) => {
-			let tbl_entry = &jump_table[&op.address];
-			op.jump_address = opcodes[tbl_entry[&0]].actual_address();
+			op.jump_address = resolve_jump(jump_table, op.jump_address, op.address);
 			log::debug!(
         "Adjusting conditional jump Opcode ({:02X}) at 0x{:08X} (actual {:08X}) to jump to: {:08X}",
         op.opcode,
@@ -403,8 +584,7 @@ fn adjust_single_opcode(
 			op.into()
 		}
 		Opcode::JNZ(mut op) | Opcode::JZ(mut op) => {
-			let tbl_entry = &jump_table[&op.address];
-			op.jump_address = opcodes[tbl_entry[&0]].actual_address();
+			op.jump_address = resolve_jump(jump_table, op.jump_address, op.address);
 			log::debug!(
         "Adjusting conditional jump Opcode ({:02X}) at 0x{:08X} (actual {:08X}) to jump to: {:08X}",
         op.opcode,
@@ -417,18 +597,16 @@ fn adjust_single_opcode(
 
 		Opcode::Switch(mut op) => {
 			for branch in op.arms.iter_mut() {
-				let tbl_entry = &jump_table[&op.address];
-				branch.jump_address = opcodes[tbl_entry[&branch.index]].actual_address()
+				branch.jump_address = resolve_jump(jump_table, branch.jump_address, op.address);
 			}
 			Opcode::Switch(op)
 		}
 		Opcode::OP_CHOICE(mut op) | Opcode::OP_MENU_CHOICE(mut op) => {
-			for (idx, branch) in op.choices.iter_mut().enumerate() {
-				let tbl_entry = &jump_table[&op.address];
-				if tbl_entry.is_empty() {
+			for branch in op.choices.iter_mut() {
+				if branch.jump_address == 0 {
 					continue;
 				}
-				branch.jump_address = opcodes[tbl_entry[&(idx as u16)]].actual_address()
+				branch.jump_address = resolve_jump(jump_table, branch.jump_address, op.address);
 			}
 			op.into()
 		}
@@ -436,24 +614,303 @@ fn adjust_single_opcode(
 			let mut res: Vec<_> = vec![];
 			for opcode in ins_opcode.contents.into_iter() {
 				log::debug!("Entering insert adjustment.");
-				let adjustment = adjust_single_opcode(opcode, jump_table, opcodes).unwrap();
+				let adjustment = adjust_single_opcode(opcode, jump_table).unwrap();
 				log::debug!("Leaving insert adjustment.");
 				res.push(adjustment);
 			}
 
 			Opcode::OP_Insert(InsertOpcode { contents: res })
 		}
+		Opcode::OP_Replace(mut replace) => {
+			replace.replacement = replace
+				.replacement
+				.into_iter()
+				.map(|opcode| adjust_single_opcode(opcode, jump_table).unwrap())
+				.collect();
+			Opcode::OP_Replace(replace)
+		}
 		opcode @ _ => opcode,
 	};
 
 	Some(opcode)
 }
 
+/// Finds where the opcode at `target` ended up. Scripts are validated before they're serialized,
+/// so a missing target is a bug.
+fn resolve_jump(jump_table: &HashMap<u32, u32>, target: u32, source: u32) -> u32 {
+	jump_table
+		.get(&target)
+		.copied()
+		.ok_or_else(|| {
+			anyhow!("Could not find jump target 0x{target:08X} for the jump at 0x{source:08X}")
+		})
+		.unwrap()
+}
+
 #[cfg(test)]
 mod tests {
 	use std::collections::{HashMap, HashSet};
 
-	use crate::opcodescript::Script;
+	use crate::opcodescript::{
+		DeleteOpcode, InsertOpcode, Opcode, Quirks, RecoveredRegion, ReplaceOpcode, Script,
+		ValidationError,
+	};
+
+	#[test]
+	fn test_recovering_disassembly() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x4A, 0xFF, 0xFF, // wait
+			0x3D, 0x01, // not an opcode
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'a', b'b', 0x00, // text
+			0x4A, 0xFF, 0xFF, // wait
+			0x05, 0x00, // return
+		];
+
+		let (script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_some());
+		assert_eq!(script.opcodes.len(), 1);
+
+		let (script, error, regions) = Script::new_recovering(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_some());
+		assert_eq!(
+			regions,
+			vec![RecoveredRegion {
+				address: 0x07,
+				size: 2
+			}]
+		);
+		assert!(matches!(&script.opcodes[1], Opcode::OP_RawBytes(op) if op.bytes == [0x3D, 0x01]));
+		assert!(matches!(&script.opcodes[2], Opcode::OP_TEXTBOX_DISPLAY(op) if op.unicode == "ab"));
+		assert_eq!(script.binary_serialize(), data);
+	}
+
+	#[test]
+	fn test_validate() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x01, 0x09, 0x00, 0x00, 0x00, // jmp 0x09
+			0x4A, 0xFF, 0xFF, // wait
+			0x05, 0x00, // return
+		];
+		let (mut script, _) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(script.validate().is_empty());
+
+		if let Opcode::OP_DIRECT_JUMP(op) = &mut script.opcodes[0] {
+			op.jump_address = 0x0A;
+		}
+		// An inserted copy of the wait doesn't make its address ambiguous, but a second one at the
+		// top level does.
+		let wait = script.opcodes[1].clone();
+		script.opcodes.insert(
+			2,
+			Opcode::OP_Insert(InsertOpcode {
+				contents: vec![wait.clone()],
+			}),
+		);
+		assert_eq!(
+			script.validate(),
+			vec![ValidationError::DanglingTarget {
+				source: 0x04,
+				target: 0x0A,
+				kind: "Direct jump".to_string()
+			}]
+		);
+
+		script.opcodes.insert(3, wait);
+		assert_eq!(
+			script.validate(),
+			vec![
+				ValidationError::DuplicateAddress {
+					address: 0x09,
+					count: 2
+				},
+				ValidationError::DanglingTarget {
+					source: 0x04,
+					target: 0x0A,
+					kind: "Direct jump".to_string()
+				},
+			]
+		);
+	}
+
+	#[test]
+	fn test_duplicate_inserted_addresses() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00,
+			0x4A, 0xFF, 0xFF, // wait
+			0x05, 0x00, // return
+		];
+		let (mut script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		// Two inserts copy-pasted with the same text, at an address the script doesn't have.
+		let mut text = script.opcodes[0].clone();
+		if let Opcode::OP_TEXTBOX_DISPLAY(op) = &mut text {
+			op.address = 0x40;
+		}
+		for idx in [2, 3] {
+			script.opcodes.insert(
+				idx,
+				Opcode::OP_Insert(InsertOpcode {
+					contents: vec![text.clone()],
+				}),
+			);
+		}
+
+		assert_eq!(
+			script.validate(),
+			vec![ValidationError::DuplicateAddress {
+				address: 0x40,
+				count: 2
+			}]
+		);
+	}
+
+	#[test]
+	fn test_delete_and_replace() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x01, 0x0C, 0x00, 0x00, 0x00, // jmp 0x0C
+			0x4A, 0xFF, 0xFF, // wait
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00, // 0x0C
+			0x4A, 0xFF, 0xFF, // wait
+			0x05, 0x00, // return
+		];
+		let (mut script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		let mut text = script.opcodes[2].clone();
+		if let Opcode::OP_TEXTBOX_DISPLAY(op) = &mut text {
+			op.unicode = "B".to_string();
+		}
+		let deleted = script.opcodes.remove(2);
+		script.opcodes.insert(
+			2,
+			Opcode::OP_Delete(DeleteOpcode {
+				contents: vec![deleted],
+				actual_address: 0,
+			}),
+		);
+		let wait = script.opcodes.remove(1);
+		script.opcodes.insert(
+			1,
+			Opcode::OP_Replace(ReplaceOpcode {
+				original: vec![wait],
+				replacement: vec![text],
+				actual_address: 0,
+			}),
+		);
+
+		assert!(script.validate().is_empty());
+		#[rustfmt::skip]
+		assert_eq!(
+			script.binary_serialize(),
+			[
+				0x04, 0x00, 0x00, 0x00,
+				0x01, 0x10, 0x00, 0x00, 0x00, // now jumps to the wait after the deleted text
+				0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00,
+				0x4A, 0xFF, 0xFF, // 0x10
+				0x05, 0x00,
+			]
+		);
+	}
+
+	#[test]
+	fn test_jumps_into_and_out_of_inserts() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x01, 0x0C, 0x00, 0x00, 0x00, // jmp 0x0C
+			0x4A, 0xFF, 0xFF, // wait
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00, // 0x0C
+			0x4A, 0xFF, 0xFF, // wait, 0x13
+			0x05, 0x00, // return
+		];
+		let (mut script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		let mut text = script.opcodes[2].clone();
+		if let Opcode::OP_TEXTBOX_DISPLAY(op) = &mut text {
+			op.address = 0x1000;
+			op.unicode = "B".to_string();
+		}
+		let mut jump_out = script.opcodes[0].clone();
+		if let Opcode::OP_DIRECT_JUMP(op) = &mut jump_out {
+			op.address = 0x1001;
+			op.jump_address = 0x13;
+		}
+		if let Opcode::OP_DIRECT_JUMP(op) = &mut script.opcodes[0] {
+			op.jump_address = 0x1000;
+		}
+		script.opcodes.insert(
+			2,
+			Opcode::OP_Insert(InsertOpcode {
+				contents: vec![text, jump_out],
+			}),
+		);
+
+		assert!(script.validate().is_empty());
+		#[rustfmt::skip]
+		assert_eq!(
+			script.binary_serialize(),
+			[
+				0x04, 0x00, 0x00, 0x00,
+				0x01, 0x0C, 0x00, 0x00, 0x00, // into the insert
+				0x4A, 0xFF, 0xFF,
+				0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00, // 0x0C
+				0x01, 0x1F, 0x00, 0x00, 0x00, // and back out
+				0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00,
+				0x4A, 0xFF, 0xFF, // 0x1F
+				0x05, 0x00,
+			]
+		);
+	}
+
+	#[test]
+	fn test_insert_reusing_an_address() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x01, 0x0C, 0x00, 0x00, 0x00, // jmp 0x0C
+			0x4A, 0xFF, 0xFF, // wait
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00, // 0x0C
+			0x05, 0x00, // return
+		];
+		let (mut script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		// A copy of the text, keeping its address, inserted right before it. Jumps to the address
+		// still land on the original, since it isn't nested in anything.
+		let mut text = script.opcodes[2].clone();
+		if let Opcode::OP_TEXTBOX_DISPLAY(op) = &mut text {
+			op.unicode = "B".to_string();
+		}
+		script.opcodes.insert(
+			2,
+			Opcode::OP_Insert(InsertOpcode {
+				contents: vec![text],
+			}),
+		);
+
+		#[rustfmt::skip]
+		assert_eq!(
+			script.binary_serialize(),
+			[
+				0x04, 0x00, 0x00, 0x00,
+				0x01, 0x13, 0x00, 0x00, 0x00, // still jumps to the original text
+				0x4A, 0xFF, 0xFF,
+				0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00,
+				0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00, // 0x13
+				0x05, 0x00,
+			]
+		);
+	}
 
 	// #[test]
 	// fn test_thing() {
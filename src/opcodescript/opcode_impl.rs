@@ -557,7 +557,23 @@ impl Quirks {
 	}
 }
 
+/// The error for an opcode that only exists in some games, when none of the quirks that would make
+/// sense of it are set.
+pub(crate) fn missing_quirk(opcode: u8, address: usize) -> anyhow::Error {
+	match opcode {
+		0x0F => anyhow!("Bad use of 0F without quirks at address 0x{address:08X}. This script might be meant for Secret Garden (add the \"sg\" quirk) or an xbox 360 game (add the \"x360\" quirk)."),
+		0x3F => anyhow!("Bad use of 3F without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."),
+		0x46 => anyhow!("Bad use of 46 without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."),
+		0x7A => anyhow!("Bad use of 7A without quirks at address 0x{address:08X}. This script might be meant for Secret Garden 2 (add the \"sg2\" quirk) or root double (add the \"rootx360\" quirk) or Library Party (add the \"lp\" quirk)."),
+		0x7D => anyhow!("Bad use of 7D without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."),
+		0x92 => anyhow!("Bad use of 92 without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."),
+		0x93 => anyhow!("Bad use of 93 without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."),
+		_ => anyhow::Error::new(YetiError::ParseOpcode { opcode, address }),
+	}
+}
+
 impl Opcode {
+	// The table in view.rs mirrors this one for OpcodeView, so changes here have to be made there too.
 	pub fn eat(address: usize, input: &[u8], quirks: Quirks) -> Result<Self> {
 		let opcode = input[address];
 		log::debug!("Got opcode 0x{opcode:02X} at address 0x{address:08X}.");
@@ -591,7 +607,7 @@ impl Opcode {
 				} else if quirks.contains(Quirks::XBox) {
 					Ok(Self::OP_0F_XBOX(B8::new(address, input)?))
 				} else {
-					anyhow!("Bad use of 0F without quirks at address 0x{address:08X}. This script might be meant for Secret Garden (add the \"sg\" quirk) or an xbox 360 game (add the \"x360\" quirk).").wrap_err()
+					Err(missing_quirk(0x0F, address))
 				}
 			}
 
@@ -663,7 +679,7 @@ impl Opcode {
 				if quirks.contains(Quirks::LibraryParty) {
 					Ok(Self::OP_3F_LP(B3::new(address, input)?))
 				} else {
-					Err(anyhow!("Bad use of 3F without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."))
+					Err(missing_quirk(0x3F, address))
 				}
 			}
 
@@ -695,9 +711,7 @@ impl Opcode {
 				if quirks.contains(Quirks::LibraryParty) {
 					Ok(Self::OP_46_LP_CHARNAME(StringOpcode2::new(address, input)?))
 				} else {
-					Err(anyhow!(
-						"Bad use of 46 without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."
-					))
+					Err(missing_quirk(0x46, address))
 				}
 			}
 			0x47 => {
@@ -750,7 +764,7 @@ impl Opcode {
 				} else if quirks.contains(Quirks::LibraryParty) {
 					Ok(Self::OP_7A_LP_B10(B10::new(address, input)?))
 				} else {
-					Err(anyhow!("Bad use of 7A without quirks at address 0x{address:08X}. This script might be meant for Secret Garden 2 (add the \"sg2\" quirk) or root double (add the \"rootx360\" quirk) or Library Party (add the \"lp\" quirk)."))
+					Err(missing_quirk(0x7A, address))
 				}
 			}
 			0x7B => {
@@ -764,7 +778,7 @@ impl Opcode {
 				if quirks.contains(Quirks::LibraryParty) {
 					Ok(Self::OP_7D_LP(B2::new(address, input)?))
 				} else {
-					Err(anyhow!("Bad use of 7D without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."))
+					Err(missing_quirk(0x7D, address))
 				}
 			}
 			0x80 => Ok(Self::OP_80_PHANTOM(B4::new(address, input)?)),
@@ -797,14 +811,14 @@ impl Opcode {
 				if quirks.contains(Quirks::LibraryParty) {
 					Ok(Self::OP_92_LP(B14::new(address, input)?))
 				} else {
-					Err(anyhow!("Bad use of 92 without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."))
+					Err(missing_quirk(0x92, address))
 				}
 			}
 			0x93 => {
 				if quirks.contains(Quirks::LibraryParty) {
 					Ok(Self::OP_93_LP(B8::new(address, input)?))
 				} else {
-					Err(anyhow!("Bad use of 93 without quirks at address 0x{address:08X}. This script might be meant for Library Party (add the \"lp\" quirk)."))
+					Err(missing_quirk(0x93, address))
 				}
 			}
 			0xFF => Ok(Self::OP_FF(S::new(address, input)?)),
@@ -821,6 +835,27 @@ impl Opcode {
 		})
 	}
 
+	/// Every address that jumps can use to reach this opcode. Deleted and replaced opcodes can
+	/// still be jumped to by any of their original addresses, while tips borrow the address of
+	/// the line they explain and can't be jumped to at all. Inserts have no address of their own,
+	/// jumps land on the opcodes inside them.
+	pub fn jump_addresses(&self) -> Vec<u32> {
+		match self {
+			Opcode::OP_Delete(op) => op
+				.contents
+				.iter()
+				.flat_map(Opcode::jump_addresses)
+				.collect(),
+			Opcode::OP_Replace(op) => op
+				.original
+				.iter()
+				.flat_map(Opcode::jump_addresses)
+				.collect(),
+			Opcode::OP_Insert(_) | Opcode::Tip(_) => vec![],
+			_ => vec![self.address()],
+		}
+	}
+
 	pub fn size(&self) -> usize {
 		crate::opcode_common_action!(self, op, { op.size() }, { op.size() })
 	}
@@ -853,6 +888,8 @@ impl Opcode {
 		})
 	}
 
+	/// Sets where the opcode starts in the packed script. The contents of inserts and
+	/// replacements are laid out one after another from there.
 	pub fn set_actual_address(&mut self, actual_address: usize) {
 		crate::opcode_common_action!(
 			self,
@@ -860,7 +897,13 @@ impl Opcode {
 			{
 				op.set_actual_address(actual_address as u32);
 			},
-			{ op.contents[0].set_actual_address(actual_address) }
+			{
+				let mut actual_address = actual_address;
+				for opcode in op.contents.iter_mut() {
+					opcode.set_actual_address(actual_address);
+					actual_address += opcode.size();
+				}
+			}
 		)
 	}
 }
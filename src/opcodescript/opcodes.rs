@@ -585,6 +585,242 @@ impl SizedOpcode for InsertOpcode {
 	}
 }
 
+/// Removes opcodes from a script. The removed opcodes are kept here so it's clear what was
+/// removed, but they aren't serialized.
+///
+/// Anything that jumped to one of the removed opcodes jumps to whatever comes after this instead.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DeleteOpcode {
+	pub contents: Vec<Opcode>,
+	#[serde(skip)]
+	pub actual_address: u32,
+}
+
+impl SizedOpcode for DeleteOpcode {
+	fn size(&self) -> usize {
+		0
+	}
+}
+
+impl BinarySerialize for DeleteOpcode {
+	fn binary_serialize(&self) -> Vec<u8> {
+		vec![]
+	}
+}
+
+impl Opcodelike for DeleteOpcode {
+	fn address(&self) -> u32 {
+		self.contents
+			.first()
+			.map(Opcode::address)
+			.unwrap_or(u32::MAX)
+	}
+
+	fn opcode(&self) -> u8 {
+		0
+	}
+
+	fn actual_address(&self) -> u32 {
+		self.actual_address
+	}
+
+	fn set_actual_address(&mut self, new_addr: u32) {
+		self.actual_address = new_addr;
+	}
+}
+
+/// Replaces opcodes in a script with new ones. Like [`DeleteOpcode`], the original opcodes are
+/// only kept for reference.
+///
+/// Anything that jumped to one of the original opcodes jumps to the first replacement opcode
+/// instead, or to whatever comes after this if there are no replacement opcodes.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ReplaceOpcode {
+	pub original: Vec<Opcode>,
+	pub replacement: Vec<Opcode>,
+	#[serde(skip)]
+	pub actual_address: u32,
+}
+
+impl SizedOpcode for ReplaceOpcode {
+	fn size(&self) -> usize {
+		self.replacement.iter().map(Opcode::size).sum()
+	}
+}
+
+impl BinarySerialize for ReplaceOpcode {
+	fn binary_serialize(&self) -> Vec<u8> {
+		self.replacement
+			.iter()
+			.flat_map(Opcode::binary_serialize)
+			.collect()
+	}
+}
+
+impl Opcodelike for ReplaceOpcode {
+	fn address(&self) -> u32 {
+		self.original
+			.first()
+			.map(Opcode::address)
+			.unwrap_or(u32::MAX)
+	}
+
+	fn opcode(&self) -> u8 {
+		0
+	}
+
+	fn actual_address(&self) -> u32 {
+		self.actual_address
+	}
+
+	fn set_actual_address(&mut self, new_addr: u32) {
+		self.actual_address = new_addr;
+		let mut actual_address = new_addr as usize;
+		for opcode in self.replacement.iter_mut() {
+			opcode.set_actual_address(actual_address);
+			actual_address += opcode.size();
+		}
+	}
+}
+
+/// A translation note in its own textbox, shown after the line it explains if the player's tip
+/// level allows it.
+///
+/// This is shorthand for the [`Custom77`] sequence described in the readme, and expands to it
+/// when serialized, with the skip filled in.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TipOpcode {
+	/// The address of the line the tip explains, which is how translation documents refer to it.
+	#[serde(serialize_with = "crate::opcodescript::opcodes::serialize_hex_u32")]
+	pub address: u32,
+	#[serde(skip)]
+	pub actual_address: u32,
+	/// Same as [`Custom77::condition`].
+	pub level: u8,
+	pub text: String,
+}
+
+impl TipOpcode {
+	/// The opcodes this tip stands for.
+	pub fn expand(&self) -> Vec<Opcode> {
+		let address = self.address;
+		vec![
+			Opcode::OP_CUSTOM_TIP_77(Custom77 {
+				address,
+				actual_address: 0,
+				opcode: 0x77,
+				condition: self.level,
+				skip: 4,
+				skip_bytes: 0,
+			}),
+			Opcode::OP_CLEAR_SCREEN(BasicOpcode4 {
+				address,
+				actual_address: 0,
+				opcode: 0x49,
+				arg1: 0xFFFF,
+				arg2: 0xFFFF,
+			}),
+			Opcode::OP_4F(BasicOpcode4 {
+				address,
+				actual_address: 0,
+				opcode: 0x4F,
+				arg1: 0x000D,
+				arg2: 0x0000,
+			}),
+			Opcode::OP_TEXTBOX_DISPLAY(StringOpcode {
+				address,
+				actual_address: 0,
+				opcode: 0x45,
+				header: [0xFF, 0xFF, 0x14, 0x00],
+				unicode: self.text.clone(),
+				notes: None,
+				translation: None,
+			}),
+			Opcode::OP_WAIT(BasicOpcode2 {
+				address,
+				actual_address: 0,
+				opcode: 0x4A,
+				arg1: 0xFFFF,
+			}),
+		]
+	}
+}
+
+impl SizedOpcode for TipOpcode {
+	fn size(&self) -> usize {
+		self.expand().iter().map(Opcode::size).sum()
+	}
+}
+
+impl BinarySerialize for TipOpcode {
+	fn binary_serialize(&self) -> Vec<u8> {
+		super::serialize_inserted(&self.expand())
+	}
+}
+
+impl Opcodelike for TipOpcode {
+	fn address(&self) -> u32 {
+		self.address
+	}
+
+	fn opcode(&self) -> u8 {
+		0x77
+	}
+
+	fn actual_address(&self) -> u32 {
+		self.actual_address
+	}
+
+	fn set_actual_address(&mut self, new_addr: u32) {
+		self.actual_address = new_addr;
+	}
+}
+
+/// A run of bytes that couldn't be disassembled, kept verbatim so the script still round trips.
+///
+/// These are only produced when disassembling in recovery mode. Jumps into the middle of one of
+/// these can't be resolved, so they're best treated as a hint that the opcode table needs fixing.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RawBytesOpcode {
+	#[serde(serialize_with = "crate::opcodescript::opcodes::serialize_hex_u32")]
+	pub address: u32,
+	#[serde(skip)]
+	pub actual_address: u32,
+	#[serde(serialize_with = "crate::opcodescript::opcodes::serialize_inline_ints_vec")]
+	pub bytes: Vec<u8>,
+}
+
+impl SizedOpcode for RawBytesOpcode {
+	fn size(&self) -> usize {
+		self.bytes.len()
+	}
+}
+
+impl BinarySerialize for RawBytesOpcode {
+	fn binary_serialize(&self) -> Vec<u8> {
+		self.bytes.clone()
+	}
+}
+
+impl Opcodelike for RawBytesOpcode {
+	fn address(&self) -> u32 {
+		self.address
+	}
+
+	/// The byte that failed to disassemble.
+	fn opcode(&self) -> u8 {
+		self.bytes.first().copied().unwrap_or_default()
+	}
+
+	fn actual_address(&self) -> u32 {
+		self.actual_address
+	}
+
+	fn set_actual_address(&mut self, new_addr: u32) {
+		self.actual_address = new_addr;
+	}
+}
+
 /// This opcode is essentially a hardcoded conditional that only checks for whether a particular
 /// flag has values below a certain level.
 ///
@@ -762,4 +998,8 @@ pub enum Opcode {
 	OP_93_LP(B8),  // : 9,
 	OP_FF(S),
 	OP_Insert(InsertOpcode), // Use this to insert new opcodes into a script. 0xFF (not retained after compilation)
+	OP_Delete(DeleteOpcode), // Use this to remove opcodes from a script (not retained after compilation)
+	OP_Replace(ReplaceOpcode), // Use this to swap opcodes for new ones (not retained after compilation)
+	OP_RawBytes(RawBytesOpcode), // Undecodable bytes kept by recovery mode.
+	Tip(TipOpcode), // A custom tip, expanded to OP_CUSTOM_TIP_77 and the opcodes it shows (not retained after compilation)
 }
@@ -0,0 +1,502 @@
+//! A borrowed, read only way to walk a script's opcodes, for tools that only need to know what the
+//! opcodes are and where they are. Unlike [`Script::new`](super::Script::new), nothing is copied
+//! out of the script and text is only decoded when it's asked for.
+
+use std::borrow::Cow;
+use std::iter::FusedIterator;
+
+use anyhow::{anyhow, Result};
+use encoding_rs::SHIFT_JIS;
+
+use super::opcode_impl::missing_quirk;
+use super::Quirks;
+use crate::util::{transmute_to_u16, transmute_to_u32, YetiError};
+
+/// How an opcode's bytes are laid out, which is all that's needed to find where it ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+	/// Always this many bytes, the opcode included.
+	Fixed(usize),
+	/// 0x44, which has a padding byte when the voice is 0xFFFF.
+	Voice,
+	/// A count followed by that many six byte arms.
+	Switch,
+	/// A line of text after this many bytes of arguments.
+	Text(usize),
+	/// 0x47, whose text comes after one or two arguments depending on the first.
+	Text47,
+	/// A list of choices, each with its own line of text.
+	Choice,
+}
+
+/// The [`Opcode`](super::Opcode) variant that [`Opcode::eat`](super::Opcode::eat) decodes this
+/// opcode as, and how it's laid out. This has to be kept in step with `eat`.
+fn layout(opcode: u8, address: usize, quirks: Quirks) -> Result<(&'static str, Layout)> {
+	use Layout::*;
+	let layout = match opcode {
+		0x00 => ("OP_RESET", Fixed(1)),
+		0x01 => ("OP_DIRECT_JUMP", Fixed(5)),
+		0x02 => ("OP_JUMP_TO_SCRIPT", Fixed(5)),
+		0x03 => ("OP_03_DIRECT_JUMP_PHANTOM", Fixed(5)),
+		0x04 => ("OP_04_JUMP_TO_SCRIPT_WITH_OFFSET_PHANTOM", Fixed(5)),
+		0x05 => ("OP_SCRIPT_RETURN", Fixed(1)),
+		0x06 => ("JE", Fixed(9)),
+		0x07 => ("JNE", Fixed(9)),
+		0x08 => ("JG", Fixed(9)),
+		0x09 => ("JGE", Fixed(9)),
+		0x0A => ("JL", Fixed(9)),
+		0x0B => ("JLE", Fixed(9)),
+		0x0C => ("JZ", Fixed(7)),
+		0x0D => ("JNZ", Fixed(7)),
+		0x0E => ("Switch", Switch),
+		0x0F if quirks.contains(Quirks::SG) => ("OP_0F_SG", Fixed(1)),
+		0x0F if quirks.contains(Quirks::XBox) => ("OP_0F_XBOX", Fixed(9)),
+		0x10 => ("OP_10", Fixed(5)),
+		0x11 => ("OP_11", Fixed(5)),
+		0x12 => ("OP_12", Fixed(5)),
+		0x13 => ("OP_13", Fixed(5)),
+		0x14 => ("OP_14", Fixed(5)),
+		0x15 => ("OP_15", Fixed(5)),
+		0x16 => ("OP_16", Fixed(5)),
+		0x17 => ("OP_17", Fixed(5)),
+		0x18 => ("OP_18_PHANTOM", Fixed(5)),
+		0x19 => ("OP_19", Fixed(9)),
+		0x1A => ("OP_1A", Fixed(5)),
+		0x1B => ("OP_1B", Fixed(1)),
+		0x1C => ("OP_1C", Fixed(1)),
+		0x1D => ("OP_1D", Fixed(7)),
+		0x1E => ("OP_1E", Fixed(11)),
+		0x1F => ("OP_1F", Fixed(13)),
+		0x20 => ("OP_20", Fixed(7)),
+		0x21 => ("OP_21", Fixed(7)),
+		0x22 => ("OP_22", Fixed(5)),
+		0x23 if quirks.intersects(Quirks::CCFC | Quirks::Phantom) => ("OP_23", Fixed(9)),
+		0x23 => ("OP_23_PSP", Fixed(7)),
+		0x24 => ("OP_24", Fixed(7)),
+		0x25 => ("OP_25", Fixed(5)),
+		0x2A => ("OP_2A", Fixed(1)),
+		0x2B => ("OP_2B", Fixed(1)),
+		0x2C => ("OP_2C", Fixed(3)),
+		0x2D => ("OP_2D", Fixed(5)),
+		0x2E => ("OP_2E", Fixed(1)),
+		0x2F => ("OP_2F", Fixed(3)),
+		0x30 => ("OP_30", Fixed(11)),
+		0x31 => ("OP_CHOICE", Choice),
+		0x32 => ("OP_MENU_CHOICE", Choice),
+		0x33 => ("OP_33", Fixed(1)),
+		0x34 => ("OP_34", Fixed(11)),
+		0x36 if quirks.contains(Quirks::LibraryParty) => ("OP_36_LP", Fixed(3)),
+		0x36 => ("OP_36", Fixed(4)),
+		0x37 => ("OP_37", Fixed(1)),
+		0x3F if quirks.contains(Quirks::LibraryParty) => ("OP_3F_LP", Fixed(4)),
+		0x39 => ("OP_39", Fixed(5)),
+		0x3A => ("OP_3A", Fixed(5)),
+		0x3B => ("OP_3B", Fixed(3)),
+		0x3C => ("OP_3C", Fixed(3)),
+		0x42 => ("OP_42", Fixed(9)),
+		0x43 if quirks.intersects(
+			Quirks::CCFC | Quirks::XBox | Quirks::XBoxRoot | Quirks::SG2 | Quirks::LibraryParty,
+		) =>
+		{
+			("OP_43", Fixed(5))
+		}
+		0x43 => ("OP_43_OLDPSP", Fixed(3)),
+		0x44 => ("OP_PLAY_VOICE", Voice),
+		0x45 => ("OP_TEXTBOX_DISPLAY", Text(4)),
+		0x46 if quirks.contains(Quirks::LibraryParty) => ("OP_46_LP_CHARNAME", Text(2)),
+		0x47 if quirks.intersects(Quirks::CCFC | Quirks::XBox | Quirks::XBoxRoot | Quirks::SG2) => {
+			("OP_FREE_TEXT_OR_CHARNAME", Text47)
+		}
+		0x47 => ("OP_47_TEXT", Text(2)),
+		0x48 => ("OP_48", Fixed(3)),
+		0x49 => ("OP_CLEAR_SCREEN", Fixed(5)),
+		0x4A => ("OP_WAIT", Fixed(3)),
+		0x4B => ("OP_4B", Fixed(5)),
+		0x4C => ("OP_4C", Fixed(7)),
+		0x4F => ("OP_4F", Fixed(5)),
+		0x51 => ("OP_51", Fixed(7)),
+		0x53 => ("OP_53_PHANTOM", Fixed(3)),
+		0x55 => ("OP_55", Text(9)),
+		0x56 if quirks.contains(Quirks::Phantom) => ("OP_56_PHANTOM", Fixed(3)),
+		0x56 => ("OP_56_SG2", Fixed(5)),
+		0x59 => ("OP_59", Fixed(1)),
+		0x5A => ("OP_5A", Fixed(1)),
+		0x5F => ("OP_5F", Fixed(1)),
+		0x66 => ("OP_66_PHANTOM", Fixed(3)),
+		0x68 => ("OP_68", Fixed(11)),
+		0x69 => ("OP_69", Fixed(3)),
+		0x6A => ("OP_6A", Fixed(5)),
+		0x6B => ("OP_6B_PHANTOM", Fixed(3)),
+		0x6C => ("OP_6C", Fixed(17)),
+		0x6E => ("OP_6E", Fixed(5)),
+		0x6F => ("OP_6F", Fixed(7)),
+		0x70 => ("OP_70", Fixed(1)),
+		0x71 => ("OP_71", Fixed(7)),
+		0x72 => ("OP_72", Fixed(5)),
+		0x74 => ("OP_74", Fixed(7)),
+		0x75 => ("OP_75", Fixed(5)),
+		0x77 => ("OP_CUSTOM_TIP_77", Fixed(4)),
+		0x7A if quirks.contains(Quirks::SG2) => ("OP_7A_SG2", Fixed(7)),
+		0x7A if quirks.contains(Quirks::XBoxRoot) => ("OP_7A_ROOT_XBOX", Fixed(11)),
+		0x7A if quirks.contains(Quirks::LibraryParty) => ("OP_7A_LP_B10", Fixed(11)),
+		0x7B if quirks.contains(Quirks::XBoxRoot) => ("OP_7B_ROOT_XBOX", Text(4)),
+		0x7B => ("OP_7B", Fixed(5)),
+		0x7D if quirks.contains(Quirks::LibraryParty) => ("OP_7D_LP", Fixed(3)),
+		0x80 => ("OP_80_PHANTOM", Fixed(5)),
+		0x81 => ("OP_81_SG2", Fixed(7)),
+		0x82 => ("OP_82", Fixed(3)),
+		0x83 => ("OP_83", Fixed(5)),
+		0x84 => ("OP_84_SG", Fixed(3)),
+		0x85 => ("OP_DEBUG_PRINT", Text(4)),
+		0x86 => ("OP_SPECIAL_TEXT", Text(4)),
+		0x87 => ("OP_87_ROOT_XBOX", Fixed(1)),
+		0x8A => ("OP_8A_ROOT_XBOX", Fixed(3)),
+		0x8B => ("OP_8B_XBOX", Fixed(5)),
+		0x8C if quirks.contains(Quirks::Phantom) => ("OP_8C_PHANTOM", Fixed(5)),
+		0x8C => ("OP_8C_XBOX", Fixed(13)),
+		0x8D => ("OP_8D_XBOX", Fixed(1)),
+		0x8E => ("OP_8E_ROOT_XBOX", Fixed(11)),
+		0x8F => ("OP_8F_ROOT_XBOX", Fixed(7)),
+		0x90 => ("OP_90_PHANTOM_CHARNAME", Text(2)),
+		0x92 if quirks.contains(Quirks::LibraryParty) => ("OP_92_LP", Fixed(15)),
+		0x93 if quirks.contains(Quirks::LibraryParty) => ("OP_93_LP", Fixed(9)),
+		0xFF => ("OP_FF", Fixed(1)),
+		0x0F | 0x3F | 0x46 | 0x7A | 0x7D | 0x92 | 0x93 => {
+			return Err(missing_quirk(opcode, address));
+		}
+		_ => {
+			return Err(anyhow::Error::new(YetiError::ParseOpcode {
+				opcode,
+				address,
+			}))
+		}
+	};
+	Ok(layout)
+}
+
+/// Where the text starting at `start` ends, not counting its terminator. Like
+/// [`get_sjis_bytes`](crate::util::get_sjis_bytes), text is cut off after 1024 bytes.
+fn text_end(data: &[u8], start: usize) -> usize {
+	let limit = data.len().min(start + 1024);
+	data[start..limit]
+		.iter()
+		.position(|&it| it == 0)
+		.map_or(limit, |it| start + it)
+}
+
+fn decode(text: &[u8]) -> Cow<'_, str> {
+	SHIFT_JIS.decode(text).0
+}
+
+/// An opcode as it sits in a script, borrowed from the script's bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeView<'a> {
+	pub address: u32,
+	/// The name of the [`Opcode`](super::Opcode) variant this would be disassembled as.
+	pub name: &'static str,
+	/// All of the opcode's bytes, starting with the opcode itself.
+	pub bytes: &'a [u8],
+	layout: Layout,
+}
+
+impl<'a> OpcodeView<'a> {
+	/// Reads the opcode at `address`, with the same quirks as [`Opcode::eat`](super::Opcode::eat).
+	pub fn new(address: usize, data: &'a [u8], quirks: Quirks) -> Result<Self> {
+		let opcode = *data.get(address).ok_or_else(|| {
+			anyhow!("There's no opcode at 0x{address:08X}, past the end of the script.")
+		})?;
+		let (name, layout) = layout(opcode, address, quirks)?;
+		let past_end =
+			|| anyhow!("Opcode 0x{opcode:02X} at 0x{address:08X} runs past the end of the script.");
+		let arg = |offset: usize| transmute_to_u16(address + offset, data).map_err(|_| past_end());
+
+		let size = match layout {
+			Layout::Fixed(size) => size,
+			Layout::Voice => match arg(3)? {
+				0xFFFF => 6,
+				_ => 5,
+			},
+			Layout::Switch => 5 + 6 * arg(3)? as usize,
+			Layout::Text(_) | Layout::Text47 => {
+				let start = address + 1 + text_offset(layout, arg(1)?);
+				if start > data.len() {
+					return Err(past_end());
+				}
+				text_end(data, start) + 1 - address
+			}
+			Layout::Choice => {
+				let count = *data.get(address + 3).ok_or_else(past_end)?;
+				let mut end = address + 7;
+				for _ in 0..count {
+					// Each choice has a six byte header and a jump address before its text.
+					if end + 10 > data.len() {
+						return Err(past_end());
+					}
+					end = text_end(data, end + 10) + 1;
+				}
+				end - address
+			}
+		};
+
+		let bytes = data.get(address..address + size).ok_or_else(past_end)?;
+		Ok(Self {
+			address: address as u32,
+			name,
+			bytes,
+			layout,
+		})
+	}
+
+	pub fn opcode(&self) -> u8 {
+		self.bytes[0]
+	}
+
+	/// How many bytes the opcode takes up in the script. Text that isn't valid Shift JIS is
+	/// re-encoded differently once it's been disassembled, so for that text this can differ from
+	/// the size of the disassembled opcode.
+	pub fn size(&self) -> usize {
+		self.bytes.len()
+	}
+
+	/// The bytes after the opcode, up to its text if it has any.
+	pub fn args(&self) -> &'a [u8] {
+		let end = match self.layout {
+			Layout::Text(_) | Layout::Text47 => 1 + text_offset(self.layout, self.arg1()),
+			Layout::Choice => 7,
+			_ => self.bytes.len(),
+		};
+		&self.bytes[1..end]
+	}
+
+	/// The Shift JIS bytes of the opcode's line of text, without the terminator. Choices have
+	/// several lines, see [`OpcodeView::choices`].
+	pub fn raw_text(&self) -> Option<&'a [u8]> {
+		match self.layout {
+			Layout::Text(_) | Layout::Text47 => {
+				Some(&self.bytes[1 + self.args().len()..self.bytes.len() - 1])
+			}
+			_ => None,
+		}
+	}
+
+	/// The opcode's line of text, decoded.
+	pub fn text(&self) -> Option<Cow<'a, str>> {
+		self.raw_text().map(decode)
+	}
+
+	/// The text of each choice, decoded, if this is a choice opcode.
+	pub fn choices(&self) -> impl Iterator<Item = Cow<'a, str>> + 'a {
+		let bytes = self.bytes;
+		let mut start = 7;
+		let mut remaining = match self.layout {
+			Layout::Choice => bytes[3],
+			_ => 0,
+		};
+		std::iter::from_fn(move || {
+			remaining = remaining.checked_sub(1)?;
+			let text = start + 10;
+			let end = text_end(bytes, text);
+			start = end + 1;
+			Some(decode(&bytes[text..end]))
+		})
+	}
+
+	fn arg1(&self) -> u16 {
+		u16::from_le_bytes([self.bytes[1], self.bytes[2]])
+	}
+}
+
+/// How many bytes of arguments come before the text of a text opcode.
+fn text_offset(layout: Layout, arg1: u16) -> usize {
+	match layout {
+		Layout::Text(offset) => offset,
+		Layout::Text47 if arg1 == 0x000D => 2,
+		_ => 4,
+	}
+}
+
+/// Walks the opcodes of a script without disassembling them, stopping where
+/// [`Script::new`](super::Script::new) would. An opcode that can't be read is returned as an error
+/// and ends the walk.
+#[derive(Debug, Clone)]
+pub struct OpcodeIter<'a> {
+	data: &'a [u8],
+	quirks: Quirks,
+	start: usize,
+	address: usize,
+	previous: Option<u8>,
+	done: bool,
+}
+
+impl<'a> OpcodeIter<'a> {
+	pub fn new(data: &'a [u8], quirks: Quirks) -> Result<Self> {
+		let start = transmute_to_u32(0, data)? as usize;
+		if start > data.len() {
+			return Err(anyhow!(
+				"The script header claims to be 0x{start:08X} bytes, but the script is only 0x{:08X}.",
+				data.len()
+			));
+		}
+		Ok(Self {
+			data,
+			quirks,
+			start,
+			address: start,
+			previous: None,
+			done: false,
+		})
+	}
+
+	pub fn header(&self) -> &'a [u8] {
+		&self.data[..self.start]
+	}
+
+	/// The bytes after the last opcode read so far. Once the walk is over, this is the footer.
+	pub fn remainder(&self) -> &'a [u8] {
+		&self.data[self.address.min(self.data.len())..]
+	}
+}
+
+impl<'a> Iterator for OpcodeIter<'a> {
+	type Item = Result<OpcodeView<'a>>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done || self.address >= self.data.len() {
+			return None;
+		}
+
+		let view = match OpcodeView::new(self.address, self.data, self.quirks) {
+			Ok(view) => view,
+			Err(e) => {
+				self.done = true;
+				return Some(Err(e));
+			}
+		};
+
+		if view.opcode() == 0x77 && u16::from_le_bytes([view.bytes[2], view.bytes[3]]) < 3 {
+			self.done = true;
+			return Some(Err(anyhow!(
+				"invalid skip offset for opcode 77 at address 0x{:08X}.",
+				view.address
+			)));
+		}
+
+		// The same check for the end of the script as Script::new.
+		if view.opcode() == 0x05
+			&& (self
+				.data
+				.get(self.address + 1)
+				.is_some_and(|it| [0x00, 0x05].contains(it))
+				|| self.previous == Some(0x02)
+				|| (!self.quirks.contains(Quirks::LibraryParty)
+					&& self.data.len() - self.address < 0x30))
+		{
+			self.done = true;
+		}
+
+		self.address += view.size();
+		self.previous = Some(view.opcode());
+		Some(Ok(view))
+	}
+}
+
+impl FusedIterator for OpcodeIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::opcodescript::{Opcode, Script};
+	use crate::util::encode_sjis;
+
+	/// The variant name of a decoded opcode.
+	fn variant_name(opcode: &Opcode) -> String {
+		let debug = format!("{opcode:?}");
+		debug[..debug.find('(').unwrap()].to_string()
+	}
+
+	#[test]
+	fn test_opcode_iter() {
+		let mut data = vec![0x04, 0x00, 0x00, 0x00];
+		data.extend([0x45, 0xFF, 0xFF, 0x14, 0x00, b'H', b'i', 0x00]);
+		data.extend([0x4A, 0xFF, 0xFF]);
+		data.extend([0x44, 0x01, 0x00, 0xFF, 0xFF, 0x00]);
+		data.extend([
+			0x0E, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+		]);
+		data.extend([0x31, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00]);
+		data.extend([0, 0, 0, 0, 0, 0, 0x04, 0x00, 0x00, 0x00, b'A', 0x00]);
+		data.extend([0, 0, 0, 0, 0, 0, 0x04, 0x00, 0x00, 0x00, b'B', b'C', 0x00]);
+		data.extend([0x47, 0x0D, 0x00, b'N', 0x00]);
+		data.extend([0x06, 0x01, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00]);
+		data.extend([0x05, 0x00, 0xAB]);
+
+		let (script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		let mut iter = OpcodeIter::new(&data, Quirks::CCFC).unwrap();
+		let views = iter.by_ref().collect::<Result<Vec<_>>>().unwrap();
+		assert_eq!(views.len(), script.opcodes.len());
+		for (view, opcode) in views.iter().zip(&script.opcodes) {
+			assert_eq!(view.address, opcode.address());
+			assert_eq!(view.size(), opcode.size());
+			assert_eq!(view.opcode(), opcode.opcode());
+			assert_eq!(view.name, variant_name(opcode));
+		}
+		assert_eq!(iter.header(), script.header.bytes);
+		assert_eq!(iter.remainder(), script.footer.bytes);
+
+		assert_eq!(views[0].args(), [0xFF, 0xFF, 0x14, 0x00]);
+		assert_eq!(views[0].text().unwrap(), "Hi");
+		assert!(matches!(views[0].text(), Some(Cow::Borrowed(_))));
+		assert_eq!(views[1].args(), [0xFF, 0xFF]);
+		assert_eq!(views[1].text(), None);
+		assert_eq!(views[4].choices().collect::<Vec<_>>(), ["A", "BC"]);
+		assert_eq!(views[5].args(), [0x0D, 0x00]);
+		assert_eq!(views[5].text().unwrap(), "N");
+	}
+
+	#[test]
+	fn test_layout_matches_eat() {
+		// Arguments that make the variable length opcodes short, then ones that make them long.
+		let mut short = vec![0x00, 0x0D, 0x00, 0x02, 0x00];
+		let mut long = vec![0x00, 0x01, 0x00, 0xFF, 0xFF];
+		for data in [&mut short, &mut long] {
+			data.extend(b"abcdefghij\0".repeat(0x9000));
+		}
+
+		// Every combination of quirks for the short arguments, but the long ones are slow enough
+		// to decode that one quirk at a time will do.
+		let every_quirk_set: Vec<_> = (0..=0xFF).map(Quirks::from_bits_truncate).collect();
+		let single_quirks: Vec<_> = (0..8)
+			.map(|bit| Quirks::from_bits_truncate(1 << bit))
+			.chain([Quirks::empty()])
+			.collect();
+
+		for (data, quirk_sets) in [(&mut short, every_quirk_set), (&mut long, single_quirks)] {
+			for quirks in quirk_sets {
+				for opcode in 0..=0xFF {
+					data[0] = opcode;
+					match (
+						Opcode::eat(0, data, quirks),
+						OpcodeView::new(0, data, quirks),
+					) {
+						(Ok(eaten), Ok(view)) => {
+							let case = format!("0x{opcode:02X} with {quirks:?}");
+							assert_eq!(view.name, variant_name(&eaten), "{case}");
+							let lossy = view
+								.text()
+								.is_some_and(|text| encode_sjis(&text) != view.raw_text().unwrap());
+							if !lossy {
+								assert_eq!(view.size(), eaten.size(), "{case}");
+							}
+						}
+						(Err(eaten), Err(view)) => assert_eq!(eaten.to_string(), view.to_string()),
+						(eaten, view) => {
+							panic!("0x{opcode:02X} with {quirks:?}: {eaten:?} and {view:?}")
+						}
+					}
+				}
+			}
+		}
+	}
+}
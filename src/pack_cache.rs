@@ -0,0 +1,184 @@
+//! A small on-disk cache of serialized scripts, used to skip re-serializing scripts whose
+//! YAML and text sources, and the configuration they're packed with, haven't changed since the
+//! last pack.
+
+use camino::{Utf8Path as Path, Utf8PathBuf as PathBuf};
+
+use crate::config::ProjectConfig;
+
+/// Name of the cache directory created next to `directory.yaml`.
+pub const CACHE_DIR_NAME: &str = ".yeti_cache";
+
+pub struct PackCache {
+	dir: PathBuf,
+	enabled: bool,
+}
+
+impl PackCache {
+	/// Opens the cache for the given yaml directory. If `clean` is set, any existing cache
+	/// entries are discarded and the cache is only written to, never read from.
+	pub fn new(top_dir: &Path, clean: bool) -> Self {
+		let dir = top_dir.join(CACHE_DIR_NAME);
+		if clean && dir.exists() {
+			if let Err(e) = std::fs::remove_dir_all(&dir) {
+				log::warn!("Could not clear pack cache at {dir}: {e}");
+			}
+		}
+		if let Err(e) = std::fs::create_dir_all(&dir) {
+			log::warn!("Could not create pack cache at {dir}, caching is disabled: {e}");
+			return Self {
+				dir,
+				enabled: false,
+			};
+		}
+
+		Self { dir, enabled: true }
+	}
+
+	/// Returns the cached bytes for `name` if they were produced from sources with the given key.
+	pub fn get(&self, name: &str, key: u64) -> Option<Vec<u8>> {
+		if !self.enabled {
+			return None;
+		}
+
+		let data = std::fs::read(self.entry_path(name)).ok()?;
+		if data.len() < 8 || data[..8] != key.to_le_bytes() {
+			return None;
+		}
+
+		Some(data[8..].to_vec())
+	}
+
+	pub fn put(&self, name: &str, key: u64, serialized: &[u8]) {
+		if !self.enabled {
+			return;
+		}
+
+		let mut data = Vec::with_capacity(8 + serialized.len());
+		data.extend(key.to_le_bytes());
+		data.extend(serialized);
+
+		let path = self.entry_path(name);
+		if let Err(e) = std::fs::write(&path, data) {
+			log::warn!("Could not write pack cache entry {path}: {e}");
+		}
+	}
+
+	fn entry_path(&self, name: &str) -> PathBuf {
+		self.dir.join(name).with_extension("opcodescript")
+	}
+}
+
+/// FNV-1a, which unlike the standard library's hashers gives the same result on every run and
+/// every build, so keys written by one pack still match in the next.
+struct KeyHasher(u64);
+
+impl KeyHasher {
+	fn new() -> Self {
+		Self(0xCBF2_9CE4_8422_2325)
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.0 ^= byte as u64;
+			self.0 = self.0.wrapping_mul(0x0000_0100_0000_01B3);
+		}
+	}
+
+	/// Writes a length prefixed field, so that neighbouring fields can't run into each other.
+	fn field(&mut self, bytes: Option<&[u8]>) {
+		match bytes {
+			Some(bytes) => {
+				self.write(&[1]);
+				self.write(&(bytes.len() as u64).to_le_bytes());
+				self.write(bytes);
+			}
+			None => self.write(&[0]),
+		}
+	}
+}
+
+/// Computes a key for the parts of the project configuration that change how scripts are
+/// serialized: the textbox width and page breaking, the italic glyph table and the quirks.
+pub fn config_key(config: &ProjectConfig) -> u64 {
+	let mut hasher = KeyHasher::new();
+	hasher.field(Some(&(config.textbox_width() as u64).to_le_bytes()));
+	hasher.field(Some(&[config.auto_page_break.unwrap_or_default() as u8]));
+	hasher.field(config.quirks.as_deref().map(str::as_bytes));
+	// The table is read from the file, so that's what matters rather than where it is.
+	let italic_map = config
+		.italic_map
+		.as_ref()
+		.map(|path| std::fs::read(path).unwrap_or_else(|_| path.as_str().as_bytes().to_vec()));
+	hasher.field(italic_map.as_deref());
+	hasher.0
+}
+
+/// Computes the cache key for a script from its YAML source and translation doc, and the
+/// [`config_key`] of the configuration it's packed with.
+///
+/// The tool version is mixed in so that a new release never reuses bytes serialized by an older one.
+pub fn content_hash(yaml: &[u8], text: Option<&str>, config_key: u64) -> u64 {
+	let mut hasher = KeyHasher::new();
+	hasher.field(Some(env!("CARGO_PKG_VERSION").as_bytes()));
+	hasher.field(Some(&config_key.to_le_bytes()));
+	hasher.field(Some(yaml));
+	hasher.field(text.map(str::as_bytes));
+	hasher.0
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{config_key, content_hash, PackCache};
+	use crate::config::ProjectConfig;
+	use camino::Utf8PathBuf;
+
+	#[test]
+	fn test_cache_roundtrip() {
+		let dir = Utf8PathBuf::from_path_buf(std::env::temp_dir())
+			.unwrap()
+			.join(format!("yeti_pack_cache_test_{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+
+		let config = config_key(&ProjectConfig::default());
+		let key = content_hash(b"opcodes: []", Some("text"), config);
+		assert_eq!(key, content_hash(b"opcodes: []", Some("text"), config));
+		assert_ne!(key, content_hash(b"opcodes: []", None, config));
+		assert_ne!(key, content_hash(b"opcodes: []", Some(""), config));
+
+		for changed in [
+			ProjectConfig {
+				textbox_width: Some(48),
+				..Default::default()
+			},
+			ProjectConfig {
+				auto_page_break: Some(true),
+				..Default::default()
+			},
+			ProjectConfig {
+				quirks: Some("lp".to_string()),
+				..Default::default()
+			},
+			ProjectConfig {
+				italic_map: Some("italic_map.json".into()),
+				..Default::default()
+			},
+		] {
+			assert_ne!(
+				key,
+				content_hash(b"opcodes: []", Some("text"), config_key(&changed))
+			);
+		}
+
+		let cache = PackCache::new(&dir, false);
+		assert_eq!(cache.get("0001.yaml", key), None);
+		cache.put("0001.yaml", key, &[1, 2, 3]);
+		assert_eq!(cache.get("0001.yaml", key), Some(vec![1, 2, 3]));
+		assert_eq!(cache.get("0001.yaml", key + 1), None);
+
+		let cache = PackCache::new(&dir, true);
+		assert_eq!(cache.get("0001.yaml", key), None);
+
+		std::fs::remove_dir_all(&dir).unwrap();
+	}
+}
@@ -0,0 +1,311 @@
+//! Carries a translation over to another release of the same game, for example from the PC
+//! release of Cross Channel to the Vita one.
+//!
+//! Scripts are paired up by index, and within a pair, lines are matched by their original text.
+//! Lines are lined up in order first, so repeated lines go to the right place, and whatever is
+//! left is matched by text anywhere in the script. Both scenarios must already be unpacked, each
+//! with its own quirks.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::opcodescript::{Opcode, Script};
+use crate::scenario_diff::{align, Step};
+
+/// A translated line or tip that has no counterpart in the target scenario.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnplacedLine {
+	pub script: usize,
+	pub address: u32,
+	pub original: String,
+	pub translation: String,
+	/// Whether `translation` is the text of a tip for the line, rather than its translation.
+	pub tip: bool,
+}
+
+/// A tip to add to the target, next to the line at `address`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortedTip {
+	pub script: usize,
+	pub address: u32,
+	pub level: u8,
+	pub text: String,
+}
+
+/// A translation and notes to copy to a text slot of the target.
+#[derive(Clone, Debug, PartialEq)]
+struct PortedLine {
+	script: usize,
+	slot: usize,
+	translation: Option<String>,
+	notes: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PortSummary {
+	/// How many lines and tips were ported, per script.
+	pub ported: HashMap<usize, usize>,
+	lines: Vec<PortedLine>,
+	pub tips: Vec<PortedTip>,
+	pub unplaced: Vec<UnplacedLine>,
+}
+
+impl PortSummary {
+	/// Copies the ported translations and notes into the target. Only the fields that were ported
+	/// are changed. Tips are left to the caller, see [`PortSummary::tips`].
+	pub fn apply(&self, target: &mut [Script]) {
+		let mut slots: HashMap<usize, Vec<TextSlot>> = HashMap::new();
+		for (idx, script) in target.iter_mut().enumerate() {
+			if self.ported.contains_key(&idx) {
+				slots.insert(idx, text_slots(&mut script.opcodes));
+			}
+		}
+
+		for line in &self.lines {
+			let slot = &mut slots.get_mut(&line.script).unwrap()[line.slot];
+			if line.translation.is_some() {
+				*slot.translation = line.translation.clone();
+			}
+			if line.notes.is_some() {
+				*slot.notes = line.notes.clone();
+			}
+		}
+	}
+}
+
+/// Something with text that can be translated: a line, a name or a choice.
+struct TextSlot<'a> {
+	address: u32,
+	original: &'a str,
+	translation: &'a mut Option<String>,
+	notes: &'a mut Option<String>,
+}
+
+/// The text slots of a script, in the order they're shown, including inserted and replacement
+/// opcodes.
+fn text_slots(opcodes: &mut [Opcode]) -> Vec<TextSlot<'_>> {
+	let mut slots = vec![];
+	for opcode in opcodes {
+		match opcode {
+			Opcode::OP_Insert(insert) => slots.extend(text_slots(&mut insert.contents)),
+			Opcode::OP_Replace(replace) => slots.extend(text_slots(&mut replace.replacement)),
+			Opcode::OP_TEXTBOX_DISPLAY(op) | Opcode::OP_SPECIAL_TEXT(op) => slots.push(TextSlot {
+				address: op.address,
+				original: &op.unicode,
+				translation: &mut op.translation,
+				notes: &mut op.notes,
+			}),
+			Opcode::OP_46_LP_CHARNAME(op) | Opcode::OP_90_PHANTOM_CHARNAME(op) => {
+				slots.push(TextSlot {
+					address: op.address,
+					original: &op.unicode,
+					translation: &mut op.translation,
+					notes: &mut op.notes,
+				})
+			}
+			Opcode::OP_FREE_TEXT_OR_CHARNAME(op) => slots.push(TextSlot {
+				address: op.address,
+				original: &op.unicode,
+				translation: &mut op.translation,
+				notes: &mut op.notes,
+			}),
+			Opcode::OP_CHOICE(op) | Opcode::OP_MENU_CHOICE(op) => {
+				slots.extend(op.choices.iter_mut().map(|choice| TextSlot {
+					address: choice.address,
+					original: &choice.unicode,
+					translation: &mut choice.translation,
+					notes: &mut choice.notes,
+				}))
+			}
+			_ => {}
+		}
+	}
+	slots
+}
+
+/// The text lines are matched by. Releases often wrap lines differently, so line breaks and
+/// spaces are left out.
+fn match_key(text: &str) -> String {
+	text.replace("%N", "")
+		.chars()
+		.filter(|it| !it.is_whitespace())
+		.collect()
+}
+
+/// Matches the translated lines and tips in `source` to the lines of `target`. Use
+/// [`PortSummary::apply`] to copy them over.
+pub fn port_translations(source: &[Script], target: &[Script]) -> PortSummary {
+	let mut summary = PortSummary::default();
+
+	for (idx, source) in source.iter().enumerate() {
+		let mut source = source.clone();
+		let tips: Vec<_> = source
+			.opcodes
+			.iter()
+			.filter_map(|it| match it {
+				Opcode::Tip(tip) => Some(tip.clone()),
+				_ => None,
+			})
+			.collect();
+		let source = text_slots(&mut source.opcodes);
+		let mut target = target
+			.get(idx)
+			.map(|it| it.opcodes.clone())
+			.unwrap_or_default();
+		let target = text_slots(&mut target);
+
+		let source_keys: Vec<_> = source.iter().map(|it| match_key(it.original)).collect();
+		let target_keys: Vec<_> = target.iter().map(|it| match_key(it.original)).collect();
+
+		let mut matches: Vec<Option<usize>> = vec![None; source.len()];
+		let mut unmatched_targets: HashMap<&str, VecDeque<usize>> = HashMap::new();
+		for step in align(&source_keys, &target_keys) {
+			match step {
+				Step::Same(i, j) => matches[i] = Some(j),
+				Step::Added(j) => unmatched_targets
+					.entry(&target_keys[j])
+					.or_default()
+					.push_back(j),
+				Step::Removed(_) => {}
+			}
+		}
+		for (i, key) in source_keys.iter().enumerate() {
+			if matches[i].is_none() {
+				matches[i] = unmatched_targets
+					.get_mut(key.as_str())
+					.and_then(VecDeque::pop_front);
+			}
+		}
+
+		for (slot, &target_idx) in source.iter().zip(&matches) {
+			if slot.translation.is_none() && slot.notes.is_none() {
+				continue;
+			}
+			let Some(target_idx) = target_idx else {
+				summary.unplaced.push(UnplacedLine {
+					script: idx,
+					address: slot.address,
+					original: slot.original.to_string(),
+					translation: slot.translation.clone().unwrap_or_default(),
+					tip: false,
+				});
+				continue;
+			};
+
+			summary.lines.push(PortedLine {
+				script: idx,
+				slot: target_idx,
+				translation: slot.translation.clone(),
+				notes: slot.notes.clone(),
+			});
+			*summary.ported.entry(idx).or_default() += 1;
+		}
+
+		// Tips go with whichever line they're for.
+		for tip in tips {
+			let line = source.iter().position(|it| it.address == tip.address);
+			match line.and_then(|it| matches[it]) {
+				Some(target_idx) => {
+					summary.tips.push(PortedTip {
+						script: idx,
+						address: target[target_idx].address,
+						level: tip.level,
+						text: tip.text,
+					});
+					*summary.ported.entry(idx).or_default() += 1;
+				}
+				None => summary.unplaced.push(UnplacedLine {
+					script: idx,
+					address: tip.address,
+					original: line
+						.map(|it| source[it].original.to_string())
+						.unwrap_or_default(),
+					translation: tip.text,
+					tip: true,
+				}),
+			}
+		}
+	}
+
+	summary
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{port_translations, PortedTip};
+	use crate::opcodescript::{Opcode, Quirks, Script, TipOpcode};
+
+	fn translation(script: &Script, idx: usize) -> Option<&str> {
+		match &script.opcodes[idx] {
+			Opcode::OP_TEXTBOX_DISPLAY(op) => op.translation.as_deref(),
+			opcode => panic!("Expected a textbox, got {opcode:?}"),
+		}
+	}
+
+	#[test]
+	fn test_port_translations() {
+		#[rustfmt::skip]
+		let source: &[u8] = &[
+			0x04, 0x00, 0x00, 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'C', 0x00,
+			0x05, 0x00,
+		];
+		// Another release, with a wait before the first line, the line wrapped differently, and
+		// the last two lines swapped.
+		#[rustfmt::skip]
+		let target: &[u8] = &[
+			0x04, 0x00, 0x00, 0x00,
+			0x4A, 0xFF, 0xFF,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'%', b'N', b'A', 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'C', 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00,
+			0x05, 0x00,
+		];
+
+		let mut source = Script::new(source, Quirks::empty()).unwrap().0;
+		for (idx, text) in ["Ay", "Bee", "Sea"].iter().enumerate() {
+			let Opcode::OP_TEXTBOX_DISPLAY(op) = &mut source.opcodes[idx] else {
+				unreachable!()
+			};
+			op.translation = Some(text.to_string());
+		}
+		// A tip for the second line, and a page break in the third.
+		source.opcodes.insert(
+			2,
+			Opcode::Tip(TipOpcode {
+				address: 0x0B,
+				actual_address: 0,
+				level: 1,
+				text: "A tip".to_string(),
+			}),
+		);
+		let Opcode::OP_TEXTBOX_DISPLAY(op) = &mut source.opcodes[3] else {
+			unreachable!()
+		};
+		op.translation = Some("Sea%Pmore".to_string());
+		let mut target = vec![Script::new(target, Quirks::empty()).unwrap().0];
+
+		let summary = port_translations(&[source.clone(), source], &target);
+		summary.apply(&mut target);
+		assert_eq!(translation(&target[0], 1), Some("Ay"));
+		assert_eq!(translation(&target[0], 2), Some("Sea%Pmore"));
+		assert_eq!(translation(&target[0], 3), Some("Bee"));
+		assert_eq!(
+			summary.tips,
+			vec![PortedTip {
+				script: 0,
+				address: 0x17,
+				level: 1,
+				text: "A tip".to_string()
+			}]
+		);
+		assert_eq!(summary.ported[&0], 4);
+
+		// The second script doesn't exist in the target.
+		assert_eq!(summary.unplaced.len(), 4);
+		assert_eq!(summary.unplaced[0].script, 1);
+		assert_eq!(summary.unplaced[0].translation, "Ay");
+		assert!(summary.unplaced[3].tip);
+	}
+}
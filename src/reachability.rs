@@ -0,0 +1,240 @@
+//! Finds opcodes, and more importantly text, that no path through the scenario ever reaches.
+//!
+//! Every script listed in the directory is treated as an entry point unless told otherwise, and
+//! from there the analysis follows jumps, switch arms, choice targets, custom tips and
+//! `OP_JUMP_TO_SCRIPT` targets. Jumps to addresses that don't exist (usually because a script
+//! didn't disassemble completely) are ignored.
+
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{Context, Result};
+use camino::Utf8Path as Path;
+
+use crate::interpreter::FlatScript;
+use crate::opcodescript::{Opcode, Script};
+use crate::voice::text_of;
+
+/// Where the addresses of unreachable text found when unpacking are kept, next to
+/// `directory.yaml`.
+pub const BASELINE_FILE_NAME: &str = "unreachable.yaml";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnreachableOpcode {
+	pub script: usize,
+	pub address: u32,
+	pub opcode: u8,
+	/// The text the opcode would show, if it shows any.
+	pub text: Option<String>,
+}
+
+/// Finds every opcode that can't be reached from the starts of the `entries` scripts, or from
+/// the start of any script if no entries are given.
+pub fn find_unreachable<'a>(
+	scenario: impl IntoIterator<Item = &'a Script>,
+	entries: Option<&[usize]>,
+) -> Vec<UnreachableOpcode> {
+	let scripts: Vec<FlatScript> = scenario.into_iter().map(FlatScript::new).collect();
+	let mut reached: Vec<Vec<bool>> = scripts
+		.iter()
+		.map(|it| vec![false; it.opcodes.len()])
+		.collect();
+
+	let mut pending: Vec<(usize, usize)> = match entries {
+		Some(entries) => entries.iter().map(|&it| (it, 0)).collect(),
+		None => (0..scripts.len()).map(|it| (it, 0)).collect(),
+	};
+
+	while let Some((script_idx, idx)) = pending.pop() {
+		let Some(script) = scripts.get(script_idx) else {
+			log::warn!("Script {script_idx} doesn't exist, so nothing can be reached from it.");
+			continue;
+		};
+		if idx >= script.opcodes.len() || reached[script_idx][idx] {
+			continue;
+		}
+		reached[script_idx][idx] = true;
+
+		let opcode = script.opcodes[idx];
+		let mut jumps = vec![];
+		let falls_through = match opcode {
+			Opcode::OP_DIRECT_JUMP(op) | Opcode::OP_03_DIRECT_JUMP_PHANTOM(op) => {
+				jumps.push(op.jump_address);
+				false
+			}
+			Opcode::JE(op)
+			| Opcode::JNE(op)
+			| Opcode::JG(op)
+			| Opcode::JGE(op)
+			| Opcode::JL(op)
+			| Opcode::JLE(op) => {
+				jumps.push(op.jump_address);
+				true
+			}
+			Opcode::JZ(op) | Opcode::JNZ(op) => {
+				jumps.push(op.jump_address);
+				true
+			}
+			Opcode::Switch(op) => {
+				jumps.extend(op.arms.iter().map(|arm| arm.jump_address));
+				true
+			}
+			Opcode::OP_CHOICE(op) | Opcode::OP_MENU_CHOICE(op) => {
+				jumps.extend(
+					op.choices
+						.iter()
+						.map(|it| it.jump_address)
+						.filter(|&it| it != 0),
+				);
+				true
+			}
+			Opcode::OP_CUSTOM_TIP_77(op) => {
+				pending.push((script_idx, idx + 1 + op.skip as usize));
+				true
+			}
+			Opcode::OP_JUMP_TO_SCRIPT(op)
+			| Opcode::OP_04_JUMP_TO_SCRIPT_WITH_OFFSET_PHANTOM(op) => {
+				let target = op.target_script as usize;
+				if let Some(target_script) = scripts.get(target) {
+					let idx = target_script
+						.addresses
+						.get(&(op.jump_address as u32))
+						.copied()
+						.unwrap_or_default();
+					pending.push((target, idx));
+				}
+				true
+			}
+			Opcode::OP_SCRIPT_RETURN(_) => false,
+			_ => true,
+		};
+
+		for address in jumps {
+			match script.addresses.get(&address) {
+				Some(&target) => pending.push((script_idx, target)),
+				None => log::debug!(
+					"Jump from 0x{:08X} in script {script_idx} to 0x{address:08X} goes nowhere.",
+					opcode.address()
+				),
+			}
+		}
+		if falls_through {
+			pending.push((script_idx, idx + 1));
+		}
+	}
+
+	scripts
+		.iter()
+		.zip(reached)
+		.enumerate()
+		.flat_map(|(script_idx, (script, reached))| {
+			script
+				.opcodes
+				.iter()
+				.zip(reached)
+				.filter(|(_, reached)| !reached)
+				.map(move |(opcode, _)| UnreachableOpcode {
+					script: script_idx,
+					address: opcode.address(),
+					opcode: opcode.opcode(),
+					text: displayed_text(opcode),
+				})
+		})
+		.collect()
+}
+
+fn displayed_text(opcode: &Opcode) -> Option<String> {
+	match opcode {
+		Opcode::OP_CHOICE(op) | Opcode::OP_MENU_CHOICE(op) => Some(
+			op.choices
+				.iter()
+				.map(|it| it.unicode.as_str())
+				.collect::<Vec<_>>()
+				.join(" / "),
+		),
+		_ => text_of(opcode).map(|(_, text, _)| text.to_string()),
+	}
+}
+
+/// The addresses of unreachable text in each script.
+pub fn unreachable_text(unreachable: &[UnreachableOpcode]) -> BTreeMap<usize, Vec<u32>> {
+	let mut text: BTreeMap<usize, Vec<u32>> = BTreeMap::new();
+	for it in unreachable.iter().filter(|it| it.text.is_some()) {
+		text.entry(it.script).or_default().push(it.address);
+	}
+	text
+}
+
+pub fn write_baseline(top_dir: &Path, unreachable: &[UnreachableOpcode]) -> Result<()> {
+	let path = top_dir.join(BASELINE_FILE_NAME);
+	let data = serde_yml::to_string(&unreachable_text(unreachable))?;
+	std::fs::write(&path, data).with_context(|| format!("Could not write {path}"))
+}
+
+pub fn read_baseline(top_dir: &Path) -> Result<BTreeMap<usize, Vec<u32>>> {
+	let path = top_dir.join(BASELINE_FILE_NAME);
+	let data = std::fs::read_to_string(&path).with_context(|| format!("Could not read {path}"))?;
+	serde_yml::from_str(&data).with_context(|| format!("Could not parse {path}"))
+}
+
+/// Returns the unreachable text that wasn't already unreachable in the baseline.
+pub fn newly_unreachable<'a>(
+	baseline: &BTreeMap<usize, Vec<u32>>,
+	unreachable: &'a [UnreachableOpcode],
+) -> Vec<&'a UnreachableOpcode> {
+	let baseline: HashSet<(usize, u32)> = baseline
+		.iter()
+		.flat_map(|(&script, addresses)| addresses.iter().map(move |&it| (script, it)))
+		.collect();
+
+	unreachable
+		.iter()
+		.filter(|it| it.text.is_some() && !baseline.contains(&(it.script, it.address)))
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::{BTreeMap, HashSet};
+
+	use super::{find_unreachable, newly_unreachable, unreachable_text};
+	use crate::opcodescript::{Quirks, Script};
+
+	#[test]
+	fn test_unreachable_text() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x01, 0x10, 0x00, 0x00, 0x00, // jmp 0x10
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00, // skipped over
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00, // 0x10
+			0x01, 0x23, 0x00, 0x00, 0x00, // jmp 0x23
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'C', 0x00, // skipped over
+			0x05, 0x00, // 0x23
+		];
+		let (script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		let scenario = [script];
+		let unreachable = find_unreachable(&scenario, None);
+		let text: Vec<_> = unreachable
+			.iter()
+			.filter_map(|it| it.text.as_deref())
+			.collect();
+		assert_eq!(text, vec!["A", "C"]);
+
+		let baseline = BTreeMap::from([(0, vec![0x09])]);
+		assert_eq!(unreachable_text(&unreachable)[&0], vec![0x09, 0x1C]);
+		let new = newly_unreachable(&baseline, &unreachable);
+		assert_eq!(new.len(), 1);
+		assert_eq!(new[0].address, 0x1C);
+
+		let doc = crate::commands::tl_transform_script_with_unreachable(
+			&scenario[0],
+			&HashSet::from([0x09]),
+		);
+		assert_eq!(doc.matches("[unreachable]").count(), 1);
+		assert!(doc.contains(
+			"[unreachable]: Nothing in the scenario leads here.\n[original text @ 0x00000009]"
+		));
+	}
+}
@@ -0,0 +1,505 @@
+//! Compares two scenarios opcode by opcode, for example two platform releases of the same game,
+//! or our build against the original.
+//!
+//! Scripts are paired up by index, and their opcodes are compared by what they do rather than
+//! where they are: addresses are left out, and jump targets are replaced by the position of the
+//! opcode they land on. Text is compared separately, so an opcode whose text differs is reported
+//! as a text change instead of being removed and added again.
+
+use std::collections::HashMap;
+
+use serde_yml::{Mapping, Value};
+
+use crate::interpreter::FlatScript;
+use crate::opcodescript::Script;
+
+/// Fields that hold text, which is compared separately from the rest of the opcode.
+const TEXT_FIELDS: &[&str] = &["unicode", "text"];
+
+/// Fields that depend on where the opcode is, or on its text, rather than on what it does.
+const IGNORED_FIELDS: &[&str] = &["address", "size", "notes", "translation"];
+
+/// Scripts with more removed and added opcodes than this aren't aligned any further, since the
+/// alignment takes time and memory quadratic in the number of edits.
+const MAX_EDITS: usize = 2_000;
+
+/// An opcode as far as the comparison is concerned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiffedOpcode {
+	pub address: u32,
+	/// What the opcode does, without its address or text.
+	pub description: String,
+	pub text: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+	Added,
+	Removed,
+	/// The same kind of opcode, with different arguments.
+	Changed,
+	/// The same opcode, showing different text.
+	Text,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OpcodeChange {
+	pub kind: ChangeKind,
+	pub old: Option<DiffedOpcode>,
+	pub new: Option<DiffedOpcode>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScriptDiff {
+	/// The script only exists in the new scenario.
+	Added,
+	/// The script only exists in the old scenario.
+	Removed,
+	Changed(Vec<OpcodeChange>),
+}
+
+/// How two opcode streams line up, by index into each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Step {
+	Same(usize, usize),
+	Removed(usize),
+	Added(usize),
+}
+
+/// Every script of a scenario, described for comparison.
+pub(crate) fn describe_scenario(scenario: &[Script]) -> Vec<Vec<DiffedOpcode>> {
+	let flat: Vec<_> = scenario.iter().map(FlatScript::new).collect();
+
+	flat.iter()
+		.enumerate()
+		.map(|(idx, script)| {
+			script
+				.opcodes
+				.iter()
+				.map(|opcode| {
+					let value = serde_yml::to_value(opcode).unwrap();
+					let mut text = vec![];
+					let description = describe(&value, &flat, idx, &mut text);
+					DiffedOpcode {
+						address: opcode.address(),
+						description,
+						text,
+					}
+				})
+				.collect()
+		})
+		.collect()
+}
+
+/// Renders a serialized opcode without its address or text, collecting the text into `text`.
+fn describe(value: &Value, flat: &[FlatScript], script: usize, text: &mut Vec<String>) -> String {
+	match value {
+		Value::Tagged(tagged) => {
+			let tag = tagged.tag.to_string();
+			let inner = match &tagged.value {
+				Value::Mapping(mapping) => describe_fields(mapping, flat, script, text),
+				value => describe(value, flat, script, text),
+			};
+			format!("{} {inner}", tag.trim_start_matches('!'))
+		}
+		Value::Mapping(mapping) => format!("{{{}}}", describe_fields(mapping, flat, script, text)),
+		Value::Sequence(items) => {
+			let items: Vec<_> = items
+				.iter()
+				.map(|it| describe(it, flat, script, text))
+				.collect();
+			format!("[{}]", items.join(", "))
+		}
+		Value::String(string) => string.trim_matches('"').to_string(),
+		Value::Number(number) => number.to_string(),
+		Value::Bool(bool) => bool.to_string(),
+		Value::Null => "null".to_string(),
+	}
+}
+
+fn describe_fields(
+	mapping: &Mapping,
+	flat: &[FlatScript],
+	script: usize,
+	text: &mut Vec<String>,
+) -> String {
+	// Script jumps land in the script they name, everything else in this one.
+	let target_script = mapping
+		.get("target_script")
+		.and_then(Value::as_u64)
+		.map_or(script, |it| it as usize);
+
+	let mut fields = vec![];
+	for (key, value) in mapping {
+		let key = key.as_str().unwrap_or_default();
+		if IGNORED_FIELDS.contains(&key) {
+			continue;
+		}
+		if TEXT_FIELDS.contains(&key) {
+			text.push(value.as_str().unwrap_or_default().to_string());
+			continue;
+		}
+		let value = if key == "jump_address" {
+			jump_target(value, flat.get(target_script))
+		} else {
+			describe(value, flat, script, text)
+		};
+		fields.push(format!("{key}: {value}"));
+	}
+
+	fields.join(", ")
+}
+
+/// Where a jump lands, as the index of the opcode in the target script, since addresses differ
+/// between releases.
+fn jump_target(value: &Value, script: Option<&FlatScript>) -> String {
+	let address = value.as_str().unwrap_or_default().trim_matches('"');
+	let idx = u32::from_str_radix(address.trim_start_matches("0x"), 16)
+		.ok()
+		.and_then(|address| script?.addresses.get(&address));
+
+	match idx {
+		Some(idx) => format!("#{idx}"),
+		None => format!("{address} (missing)"),
+	}
+}
+
+/// Lines up `old` and `new` with as few removals and additions as possible, using Myers' diff
+/// algorithm.
+pub(crate) fn align<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Step> {
+	let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+	let suffix = old[prefix..]
+		.iter()
+		.rev()
+		.zip(new[prefix..].iter().rev())
+		.take_while(|(a, b)| a == b)
+		.count();
+	let a = &old[prefix..old.len() - suffix];
+	let b = &new[prefix..new.len() - suffix];
+
+	let mut steps: Vec<Step> = (0..prefix).map(|idx| Step::Same(idx, idx)).collect();
+	match myers(a, b) {
+		Some(middle) => steps.extend(middle.into_iter().map(|step| match step {
+			Step::Same(i, j) => Step::Same(i + prefix, j + prefix),
+			Step::Removed(i) => Step::Removed(i + prefix),
+			Step::Added(j) => Step::Added(j + prefix),
+		})),
+		None => {
+			steps.extend((0..a.len()).map(|i| Step::Removed(i + prefix)));
+			steps.extend((0..b.len()).map(|j| Step::Added(j + prefix)));
+		}
+	}
+	steps.extend(
+		(0..suffix).map(|idx| Step::Same(old.len() - suffix + idx, new.len() - suffix + idx)),
+	);
+
+	steps
+}
+
+/// The shortest edit script between `a` and `b`, or `None` if it needs more than [`MAX_EDITS`]
+/// edits.
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<Step>> {
+	let (n, m) = (a.len() as isize, b.len() as isize);
+	let max = (a.len() + b.len()).min(MAX_EDITS) as isize;
+	let offset = max + 1;
+	let mut v = vec![0isize; 2 * max as usize + 3];
+	// For each number of edits, the furthest x reached on diagonals -d..=d.
+	let mut trace: Vec<Vec<isize>> = vec![];
+
+	let mut end = None;
+	'search: for d in 0..=max {
+		for k in (-d..=d).step_by(2) {
+			let mut x = if k == -d
+				|| (k != d && v[(offset + k - 1) as usize] < v[(offset + k + 1) as usize])
+			{
+				v[(offset + k + 1) as usize]
+			} else {
+				v[(offset + k - 1) as usize] + 1
+			};
+			let mut y = x - k;
+			while x < n && y < m && a[x as usize] == b[y as usize] {
+				x += 1;
+				y += 1;
+			}
+			v[(offset + k) as usize] = x;
+			if x >= n && y >= m {
+				trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+				end = Some(d);
+				break 'search;
+			}
+		}
+		trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+	}
+	let end = end?;
+
+	let mut steps = vec![];
+	let (mut x, mut y) = (n, m);
+	for d in (1..=end).rev() {
+		let previous = &trace[d as usize - 1];
+		let at = |k: isize| previous[(k + d - 1) as usize];
+		let k = x - y;
+		let previous_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+			k + 1
+		} else {
+			k - 1
+		};
+		let previous_x = at(previous_k);
+		let previous_y = previous_x - previous_k;
+
+		while x > previous_x && y > previous_y {
+			x -= 1;
+			y -= 1;
+			steps.push(Step::Same(x as usize, y as usize));
+		}
+		if previous_k == k + 1 {
+			steps.push(Step::Added(previous_y as usize));
+		} else {
+			steps.push(Step::Removed(previous_x as usize));
+		}
+		(x, y) = (previous_x, previous_y);
+	}
+	while x > 0 && y > 0 {
+		x -= 1;
+		y -= 1;
+		steps.push(Step::Same(x as usize, y as usize));
+	}
+
+	steps.reverse();
+	Some(steps)
+}
+
+/// The name of the opcode a description is for.
+fn opcode_name(description: &str) -> &str {
+	description.split(' ').next().unwrap_or_default()
+}
+
+/// Compares two described scripts. Removed and added opcodes of the same kind next to each
+/// other are paired up as changes.
+pub fn diff_script(old: &[DiffedOpcode], new: &[DiffedOpcode]) -> Vec<OpcodeChange> {
+	let old_keys: Vec<_> = old.iter().map(|it| &it.description).collect();
+	let new_keys: Vec<_> = new.iter().map(|it| &it.description).collect();
+	let steps = align(&old_keys, &new_keys);
+
+	let mut changes = vec![];
+	let mut removed: Vec<usize> = vec![];
+	let mut added: Vec<usize> = vec![];
+	let flush = |removed: &mut Vec<usize>, added: &mut Vec<usize>, changes: &mut Vec<_>| {
+		let mut unpaired_added: Vec<Option<usize>> = added.drain(..).map(Some).collect();
+		for i in removed.drain(..) {
+			let name = opcode_name(&old[i].description);
+			let pair = unpaired_added
+				.iter_mut()
+				.find(|it| it.is_some_and(|j| opcode_name(&new[j].description) == name))
+				.and_then(Option::take);
+			changes.push(OpcodeChange {
+				kind: if pair.is_some() {
+					ChangeKind::Changed
+				} else {
+					ChangeKind::Removed
+				},
+				old: Some(old[i].clone()),
+				new: pair.map(|j| new[j].clone()),
+			});
+		}
+		changes.extend(unpaired_added.into_iter().flatten().map(|j| OpcodeChange {
+			kind: ChangeKind::Added,
+			old: None,
+			new: Some(new[j].clone()),
+		}));
+	};
+
+	for step in steps {
+		match step {
+			Step::Removed(i) => removed.push(i),
+			Step::Added(j) => added.push(j),
+			Step::Same(i, j) => {
+				flush(&mut removed, &mut added, &mut changes);
+				if old[i].text != new[j].text {
+					changes.push(OpcodeChange {
+						kind: ChangeKind::Text,
+						old: Some(old[i].clone()),
+						new: Some(new[j].clone()),
+					});
+				}
+			}
+		}
+	}
+	flush(&mut removed, &mut added, &mut changes);
+
+	changes
+}
+
+/// Compares two scenarios script by script, returning only the scripts that differ.
+pub fn diff_scenarios(old: &[Script], new: &[Script]) -> Vec<(usize, ScriptDiff)> {
+	let old = describe_scenario(old);
+	let new = describe_scenario(new);
+
+	(0..old.len().max(new.len()))
+		.filter_map(|idx| {
+			let diff = match (old.get(idx), new.get(idx)) {
+				(Some(old), Some(new)) => {
+					let changes = diff_script(old, new);
+					if changes.is_empty() {
+						return None;
+					}
+					ScriptDiff::Changed(changes)
+				}
+				(Some(_), None) => ScriptDiff::Removed,
+				_ => ScriptDiff::Added,
+			};
+			Some((idx, diff))
+		})
+		.collect()
+}
+
+/// Renders the differences between two scenarios for reading, old and new lines next to each
+/// other.
+pub fn diff_report(diffs: &[(usize, ScriptDiff)], scripts: usize) -> String {
+	let mut report = String::new();
+	let mut counts: HashMap<&str, usize> = HashMap::new();
+
+	let address = |opcode: &Option<DiffedOpcode>| {
+		opcode
+			.as_ref()
+			.map(|it| format!("0x{:08X}", it.address))
+			.unwrap_or_else(|| "-".repeat(10))
+	};
+
+	for (idx, diff) in diffs {
+		let changes = match diff {
+			ScriptDiff::Added => {
+				report.push_str(&format!("Script {idx} is only in the new scenario.\n\n"));
+				continue;
+			}
+			ScriptDiff::Removed => {
+				report.push_str(&format!("Script {idx} is only in the old scenario.\n\n"));
+				continue;
+			}
+			ScriptDiff::Changed(changes) => changes,
+		};
+
+		report.push_str(&format!("Script {idx}:\n"));
+		for change in changes {
+			let (marker, name) = match change.kind {
+				ChangeKind::Added => ("+", "added"),
+				ChangeKind::Removed => ("-", "removed"),
+				ChangeKind::Changed => ("*", "changed"),
+				ChangeKind::Text => ("~", "text changes"),
+			};
+			*counts.entry(name).or_default() += 1;
+
+			let heading = format!(
+				"  {marker} {} -> {}",
+				address(&change.old),
+				address(&change.new)
+			);
+			let describe = |opcode: &DiffedOpcode| {
+				let mut line = opcode.description.clone();
+				if !opcode.text.is_empty() {
+					line.push_str(&format!(" \"{}\"", opcode.text.join("\" / \"")));
+				}
+				line
+			};
+
+			match (&change.old, &change.new) {
+				(Some(old), Some(new)) if change.kind == ChangeKind::Text => {
+					report.push_str(&format!("{heading}  {}\n", opcode_name(&old.description)));
+					for (old, new) in old.text.iter().zip(&new.text) {
+						if old != new {
+							report.push_str(&format!("      - {old}\n      + {new}\n"));
+						}
+					}
+				}
+				(Some(old), Some(new)) => {
+					report.push_str(&format!(
+						"{heading}\n      - {}\n      + {}\n",
+						describe(old),
+						describe(new)
+					));
+				}
+				(Some(opcode), None) | (None, Some(opcode)) => {
+					report.push_str(&format!("{heading}  {}\n", describe(opcode)));
+				}
+				(None, None) => {}
+			}
+		}
+		report.push('\n');
+	}
+
+	let count = |name| counts.get(name).copied().unwrap_or_default();
+	report.push_str(&format!(
+		"{} of {scripts} scripts differ: {} opcodes added, {} removed, {} changed, and {} with different text.\n",
+		diffs.len(),
+		count("added"),
+		count("removed"),
+		count("changed"),
+		count("text changes"),
+	));
+
+	report
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{align, diff_scenarios, ChangeKind, ScriptDiff, Step};
+	use crate::opcodescript::{Quirks, Script};
+
+	#[test]
+	fn test_align() {
+		let steps = align(&['a', 'b', 'c', 'd'], &['a', 'c', 'x', 'd', 'e']);
+		assert_eq!(
+			steps,
+			vec![
+				Step::Same(0, 0),
+				Step::Removed(1),
+				Step::Same(2, 1),
+				Step::Added(2),
+				Step::Same(3, 3),
+				Step::Added(4),
+			]
+		);
+		assert_eq!(align::<u8>(&[], &[]), vec![]);
+		assert_eq!(align(&[1], &[2]), vec![Step::Removed(0), Step::Added(0)]);
+	}
+
+	#[test]
+	fn test_diff_scenarios() {
+		#[rustfmt::skip]
+		let old: &[u8] = &[
+			0x04, 0x00, 0x00, 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00,
+			0x4A, 0xFF, 0xFF,
+			0x01, 0x04, 0x00, 0x00, 0x00,
+			0x05, 0x00,
+		];
+		// The same script with a longer line, which moves everything after it, a different wait
+		// and a new line at the end.
+		#[rustfmt::skip]
+		let new: &[u8] = &[
+			0x04, 0x00, 0x00, 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', b'C', 0x00,
+			0x4A, 0x10, 0x00,
+			0x01, 0x04, 0x00, 0x00, 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'D', 0x00,
+			0x05, 0x00,
+		];
+		let old = Script::new(old, Quirks::empty()).unwrap().0;
+		let new = Script::new(new, Quirks::empty()).unwrap().0;
+
+		let diffs = diff_scenarios(&[old.clone(), old.clone()], &[old.clone(), new]);
+		assert_eq!(diffs.len(), 1);
+		let (1, ScriptDiff::Changed(changes)) = &diffs[0] else {
+			panic!("Expected script 1 to change, got {diffs:?}");
+		};
+		let kinds: Vec<_> = changes.iter().map(|it| it.kind).collect();
+		assert_eq!(
+			kinds,
+			vec![ChangeKind::Text, ChangeKind::Changed, ChangeKind::Added]
+		);
+		assert_eq!(changes[0].new.as_ref().unwrap().text, vec!["BC"]);
+		assert_eq!(changes[2].new.as_ref().unwrap().address, 0x14);
+
+		let diffs = diff_scenarios(std::slice::from_ref(&old), &[old.clone(), old.clone()]);
+		assert_eq!(diffs, vec![(1, ScriptDiff::Added)]);
+	}
+}
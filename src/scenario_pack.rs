@@ -16,6 +16,43 @@ pub struct DirEntry<'a> {
 	#[serde(skip_serializing)]
 	#[serde(default)]
 	pub data: Option<&'a [u8]>,
+	/// The last 8 bytes of the directory entry. Nothing is known about them, so they're kept as
+	/// they were and written back when packing. Only stored in `directory.yaml` when non-zero.
+	#[serde(default, skip_serializing_if = "is_zeroed")]
+	#[serde(
+		serialize_with = "serialize_hex_bytes",
+		deserialize_with = "deserialize_hex_bytes"
+	)]
+	pub extra: [u8; 8],
+}
+
+fn is_zeroed(bytes: &[u8; 8]) -> bool {
+	bytes.iter().all(|&it| it == 0)
+}
+
+fn serialize_hex_bytes<S: serde::Serializer>(
+	bytes: &[u8; 8],
+	serializer: S,
+) -> Result<S::Ok, S::Error> {
+	let hex: String = bytes.iter().map(|it| format!("{it:02X}")).collect();
+	serializer.serialize_str(&hex)
+}
+
+fn deserialize_hex_bytes<'de, D: serde::Deserializer<'de>>(
+	deserializer: D,
+) -> Result<[u8; 8], D::Error> {
+	let hex: String = serde::Deserialize::deserialize(deserializer)?;
+	let hex = hex.trim().trim_start_matches("0x");
+	let error = || serde::de::Error::custom(format!("Expected 16 hex digits, got {hex}"));
+	if hex.len() != 16 || !hex.is_ascii() {
+		return Err(error());
+	}
+
+	let mut bytes = [0u8; 8];
+	for (idx, byte) in bytes.iter_mut().enumerate() {
+		*byte = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).map_err(|_| error())?;
+	}
+	Ok(bytes)
 }
 
 pub fn parse_scenario<'a>(input: &'a [u8]) -> Vec<DirEntry<'a>> {
@@ -34,11 +71,18 @@ pub fn parse_scenario<'a>(input: &'a [u8]) -> Vec<DirEntry<'a>> {
 			"Could not process entry size for entry 0x{entry_id:04X} at offset 0x{:08X}",
 			offset + 4
 		)) as usize;
+		let extra = transmute_to_array(offset + 8, input).unwrap_or_else(|_| {
+			panic!(
+				"Could not process the rest of entry 0x{entry_id:04X} at offset 0x{:08X}",
+				offset + 8
+			)
+		});
 		let entry = DirEntry {
 			name: format!("{entry_id:04}.yaml"),
 			offset: entry_offset,
 			size: entry_size,
 			data: Some(&input[entry_offset..entry_offset + entry_size]),
+			extra,
 		};
 
 		log::info!(
@@ -53,43 +97,289 @@ pub fn parse_scenario<'a>(input: &'a [u8]) -> Vec<DirEntry<'a>> {
 		entry_id += 1;
 	}
 
+	let n_extra = direntries.iter().filter(|it| !is_zeroed(&it.extra)).count();
+	if n_extra > 0 {
+		log::warn!(
+			"{n_extra} directory entries end in non-zero bytes that yeti doesn't understand. They're kept in directory.yaml and written back when packing."
+		);
+	}
+
 	direntries
 }
 
-pub fn parse_script(entry: &DirEntry, quirks: Quirks) -> anyhow::Result<Script> {
+/// How an entry's data sits relative to the data before it in the archive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spacing {
+	Adjacent,
+	/// Unused bytes between the end of the previous data and the start of this entry.
+	Gap(usize),
+	/// Bytes at the start of this entry that also belong to the previous one.
+	Overlap(usize),
+}
+
+/// Works out the spacing of every entry, in directory order, by comparing it with whatever comes
+/// before it in the file. The first entry is compared with the end of the directory.
+pub fn entry_spacing(entries: &[DirEntry]) -> Vec<Spacing> {
+	let mut by_offset: Vec<usize> = (0..entries.len()).collect();
+	by_offset.sort_by_key(|&idx| entries[idx].offset);
+
+	let mut spacing = vec![Spacing::Adjacent; entries.len()];
+	let mut previous_end = 16 * entries.len();
+	for idx in by_offset {
+		let entry = &entries[idx];
+		spacing[idx] = match entry.offset.cmp(&previous_end) {
+			std::cmp::Ordering::Equal => Spacing::Adjacent,
+			std::cmp::Ordering::Greater => Spacing::Gap(entry.offset - previous_end),
+			std::cmp::Ordering::Less => Spacing::Overlap(previous_end - entry.offset),
+		};
+		previous_end = previous_end.max(entry.offset + entry.size);
+	}
+
+	spacing
+}
+
+/// A script that is known not to disassemble cleanly for a particular game.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(from = "KnownFailureRepr")]
+pub struct KnownFailure {
+	pub script: u32,
+	/// The expected error kind, as given by [`YetiError::kind`], or `other` for errors without one.
+	/// Any kind of error is accepted if this is unset.
+	pub kind: Option<String>,
+	/// The address disassembly is expected to stop at. Any address is accepted if this is unset.
+	pub address: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KnownFailureRepr {
+	Script(u32),
+	Full {
+		script: u32,
+		kind: Option<String>,
+		address: Option<u32>,
+	},
+}
+
+impl From<KnownFailureRepr> for KnownFailure {
+	fn from(value: KnownFailureRepr) -> Self {
+		match value {
+			KnownFailureRepr::Script(script) => KnownFailure {
+				script,
+				kind: None,
+				address: None,
+			},
+			KnownFailureRepr::Full {
+				script,
+				kind,
+				address,
+			} => KnownFailure {
+				script,
+				kind,
+				address,
+			},
+		}
+	}
+}
+
+impl KnownFailure {
+	fn matches(&self, kind: &str, address: u32) -> bool {
+		self.kind.as_deref().is_none_or(|it| it == kind)
+			&& self.address.is_none_or(|it| it == address)
+	}
+}
+
+/// The scripts that are known to fail for each game, used when the project configuration
+/// doesn't list any.
+pub fn default_known_failures(quirks: Quirks) -> Vec<KnownFailure> {
+	let scripts: &[u32] = if quirks.contains(Quirks::LibraryParty) {
+		&[1, 382]
+	} else if quirks.contains(Quirks::CCFC) {
+		&[352]
+	} else {
+		&[]
+	};
+
+	scripts
+		.iter()
+		.map(|&script| KnownFailure {
+			script,
+			kind: None,
+			address: None,
+		})
+		.collect()
+}
+
+/// How disassembling a script went, compared to what was expected of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseOutcome {
+	Clean,
+	ExpectedFailure,
+	/// The script is listed as a known failure, but disassembled without problems.
+	NowPasses,
+	UnexpectedFailure,
+}
+
+pub fn parse_script(entry: &DirEntry, quirks: Quirks) -> anyhow::Result<(Script, ParseOutcome)> {
 	log::debug!("Parsing script {}.", entry.name);
 
 	let data = entry.data.unwrap();
 
-	let (script, error) = Script::new(data, quirks)?;
-
-	if let Some(error) = error {
-		let script_id = entry
-			.name
-			.split('.')
-			.next()
-			.unwrap()
-			.parse::<u32>()
-			.unwrap_or(u32::MAX);
-		let is_expected = match script_id {
-			1 | 382 => quirks.contains(Quirks::LibraryParty),
-			352 => true,
-			_ => false,
-		};
+	let (script, error, recovered_regions) = if crate::config::get().recover.unwrap_or_default() {
+		Script::new_recovering(data, quirks)?
+	} else {
+		let (script, error) = Script::new(data, quirks)?;
+		(script, error, vec![])
+	};
 
-		if is_expected {
-			log::info!(
-				"Script {} didn't parse correctly; this is expected.",
-				entry.name.split('.').next().unwrap()
-			);
-		} else {
-			log::error!(
-				"Encountered an error while decoding entry {} of size 0x{:08X}: {}",
-				entry.name.split('.').next().unwrap(),
-				entry.size,
-				error
+	let script_name = entry.name.split('.').next().unwrap();
+	let script_id = script_name.parse::<u32>().unwrap_or(u32::MAX);
+
+	let defaults;
+	let known_failures = match &crate::config::get().expected_failures {
+		Some(expected) => expected,
+		None => {
+			defaults = default_known_failures(quirks);
+			&defaults
+		}
+	};
+	let known_failure = known_failures.iter().find(|it| it.script == script_id);
+
+	let outcome = match (error, known_failure) {
+		(None, None) => ParseOutcome::Clean,
+		(None, Some(_)) => {
+			log::warn!(
+				"Script {script_name} is listed as a known failure, but now passes. It can be removed from the list."
 			);
+			ParseOutcome::NowPasses
+		}
+		(Some(error), known_failure) => {
+			let kind = error
+				.downcast_ref::<YetiError>()
+				.map_or("other", YetiError::kind);
+			let stop_address = recovered_regions
+				.first()
+				.map(|it| it.address)
+				.unwrap_or((data.len() - script.footer.bytes.len()) as u32);
+			if !recovered_regions.is_empty() {
+				log::warn!(
+					"Recovered from {} undecodable region(s) in script {script_name}: {}",
+					recovered_regions.len(),
+					recovered_regions
+						.iter()
+						.map(|it| format!("0x{:X} bytes at 0x{:08X}", it.size, it.address))
+						.collect::<Vec<_>>()
+						.join(", ")
+				);
+			}
+
+			match known_failure {
+				Some(known) if known.matches(kind, stop_address) => {
+					log::info!(
+						"Script {script_name} didn't parse correctly; this is expected ({kind} at 0x{stop_address:08X})."
+					);
+					if known.kind.is_none() || known.address.is_none() {
+						log::info!(
+							"Any failure in script {script_name} is accepted. To only accept this one, list it in expected_failures as {{ script = {script_id}, kind = \"{kind}\", address = 0x{stop_address:X} }}."
+						);
+					}
+					ParseOutcome::ExpectedFailure
+				}
+				Some(known) => {
+					log::error!(
+						"Script {script_name} was expected to fail with {} at {}, but failed with {kind} at 0x{stop_address:08X}: {error}",
+						known.kind.as_deref().unwrap_or("any error"),
+						known
+							.address
+							.map_or("any address".to_string(), |it| format!("0x{it:08X}")),
+					);
+					ParseOutcome::UnexpectedFailure
+				}
+				None => {
+					log::error!(
+						"Encountered an error while decoding entry {script_name} of size 0x{:08X}: {error}",
+						entry.size,
+					);
+					ParseOutcome::UnexpectedFailure
+				}
+			}
 		}
+	};
+
+	Ok((script, outcome))
+}
+
+/// Logs a summary of how disassembling a scenario went, returning an error if any script failed
+/// in a way that wasn't expected.
+pub fn report_parse_outcomes(outcomes: &[ParseOutcome]) -> anyhow::Result<()> {
+	let count = |outcome| outcomes.iter().filter(|&&it| it == outcome).count();
+
+	let now_passing = count(ParseOutcome::NowPasses);
+	if now_passing > 0 {
+		log::warn!("{now_passing} script(s) listed as known failures now pass.");
+	}
+
+	match count(ParseOutcome::UnexpectedFailure) {
+		0 => Ok(()),
+		n => Err(anyhow::anyhow!(
+			"{n} script(s) failed to disassemble unexpectedly."
+		)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{default_known_failures, entry_spacing, parse_scenario, DirEntry, Spacing};
+	use crate::opcodescript::Quirks;
+
+	#[test]
+	fn test_directory_extra_bytes() {
+		#[rustfmt::skip]
+		let data = [
+			0x20, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0xAB,
+			0x22, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+			0x05, 0x00, 0x05, 0x00,
+		];
+		let directory = parse_scenario(&data);
+		assert_eq!(directory[0].extra, [1, 2, 3, 4, 5, 6, 7, 0xAB]);
+
+		let yaml = serde_yml::to_string(&directory).unwrap();
+		assert!(yaml.contains("extra: '01020304050607AB'"));
+		assert_eq!(yaml.matches("extra").count(), 1);
+
+		let read: Vec<DirEntry> = serde_yml::from_str(&yaml).unwrap();
+		assert_eq!(read[0].extra, directory[0].extra);
+		assert_eq!(read[1].extra, [0; 8]);
+	}
+
+	#[test]
+	fn test_entry_spacing() {
+		let entry = |offset, size| DirEntry {
+			name: String::new(),
+			offset,
+			size,
+			data: None,
+			extra: [0; 8],
+		};
+		let entries = [entry(0x30, 4), entry(0x38, 4), entry(0x3A, 2)];
+		assert_eq!(
+			entry_spacing(&entries),
+			vec![Spacing::Adjacent, Spacing::Gap(4), Spacing::Overlap(2)]
+		);
+	}
+
+	#[test]
+	fn test_default_known_failures() {
+		let scripts = |quirks| {
+			default_known_failures(quirks)
+				.into_iter()
+				.map(|it| it.script)
+				.collect::<Vec<_>>()
+		};
+
+		// Each game only expects its own scripts to fail.
+		assert_eq!(scripts(Quirks::empty()), []);
+		assert_eq!(scripts(Quirks::CCFC), [352]);
+		assert_eq!(scripts(Quirks::LibraryParty), [1, 382]);
 	}
-	Ok(script)
 }
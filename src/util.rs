@@ -37,6 +37,18 @@ pub enum YetiError {
 	ParseOpcode { address: usize, opcode: u8 },
 }
 
+impl YetiError {
+	/// A short name for the kind of error, used to describe expected failures in configuration.
+	pub fn kind(&self) -> &'static str {
+		match self {
+			YetiError::ParseU16 { .. }
+			| YetiError::ParseU32 { .. }
+			| YetiError::ParseHeader { .. } => "truncated",
+			YetiError::ParseOpcode { .. } => "unknown-opcode",
+		}
+	}
+}
+
 impl Display for YetiError {
 	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
 		match self {
@@ -100,13 +112,18 @@ impl SJISChar {
 pub static ITALIC_MAPPING_TABLE: Lazy<HashMap<char, SJISChar>> = Lazy::new(|| {
 	let mut map = HashMap::new();
 	let file: String;
-	#[cfg(debug_assertions)]
-	{
-		file = std::fs::read_to_string("italic_map.json").unwrap();
-	}
-	#[cfg(not(debug_assertions))]
-	{
-		file = include_str!("../italic_map.json").to_owned();
+	if let Some(path) = &crate::config::get().italic_map {
+		file = std::fs::read_to_string(path)
+			.unwrap_or_else(|e| panic!("Could not read italic map {path}: {e}"));
+	} else {
+		#[cfg(debug_assertions)]
+		{
+			file = std::fs::read_to_string("italic_map.json").unwrap();
+		}
+		#[cfg(not(debug_assertions))]
+		{
+			file = include_str!("../italic_map.json").to_owned();
+		}
 	}
 
 	let data = tinyjson::JsonValue::from_str(&file).unwrap();
@@ -250,8 +267,11 @@ pub fn encode_sjis(unicode: &str) -> Vec<u8> {
 pub fn get_sjis_bytes(address: usize, input: &[u8]) -> (Vec<u8>, String) {
 	let mut size = 0usize;
 	let mut output = vec![];
-	while input[address + size] != 0 && size < 1024 {
-		output.push(input[address + size]);
+	while let Some(&byte) = input.get(address + size) {
+		if byte == 0 || size >= 1024 {
+			break;
+		}
+		output.push(byte);
 		size += 1;
 	}
 	output.push(0);
@@ -389,18 +409,23 @@ macro_rules! opcode_common_action {
 			Opcode::OP_92_LP($op) => $action,
 			Opcode::OP_93_LP($op) => $action,
 			Opcode::OP_FF($op) => $action,
+			Opcode::OP_RawBytes($op) => $action,
+			Opcode::OP_Delete($op) => $action,
+			Opcode::OP_Replace($op) => $action,
+			Opcode::Tip($op) => $action,
 			Opcode::OP_Insert($op) => $array_action,
 		}
 	};
 }
 
 pub fn fix_string(input: &str) -> String {
+	let width = crate::config::get().textbox_width() as isize;
 	let words = input.split(" ").collect::<Vec<_>>();
 	let mut output = words[0].to_string();
 
 	for word in words.iter().skip(1) {
-		let new_len = (output.len() as isize + word.len() as isize) % 60;
-		let curr_len = output.len() as isize % 60;
+		let new_len = (output.len() as isize + word.len() as isize) % width;
+		let curr_len = output.len() as isize % width;
 
 		// If we wrap over after adding the current word, we need to insert a %N
 		let spaces = if new_len - curr_len < 0 {
@@ -432,6 +457,15 @@ pub fn unescape_str(input: &str) -> String {
 		.replace("<dquote/>", "\"")
 }
 
+/// Quotes a field for a CSV file, if it needs it.
+pub fn csv_field(field: &str) -> String {
+	if field.contains([',', '"', '\n']) {
+		format!("\"{}\"", field.replace('"', "\"\""))
+	} else {
+		field.to_string()
+	}
+}
+
 pub fn fix_line(line: &str) -> String {
 	let Some(unicode_end) = line.find("unicode: ") else {
 		return line.to_string();
@@ -443,8 +477,9 @@ pub fn fix_line(line: &str) -> String {
 
 	let input_str = &line[(unicode_end + 9)..comment_start];
 
-	if input_str.len() >= 180 {
-		log::warn!("Line {input_str} contains more than 180 characters, it won't render properly in the game! For best results, split this text across two print opcodes.");
+	let max_len = 3 * crate::config::get().textbox_width();
+	if input_str.len() >= max_len {
+		log::warn!("Line {input_str} contains more than {max_len} characters, it won't render properly in the game! For best results, split this text across two print opcodes.");
 	}
 
 	line.replace(input_str, &fix_string(input_str.trim_matches([' ', '"'])))
@@ -464,6 +499,14 @@ pub fn safe_create_dir(dir: &camino::Utf8Path) -> std::io::Result<()> {
 	})
 }
 
+/// Writes `data` to a sibling temporary file first, then renames it over `path`, so readers never
+/// see a partially written file.
+pub fn write_atomic(path: &camino::Utf8Path, data: &[u8]) -> std::io::Result<()> {
+	let tmp_path = path.with_file_name(format!(".{}.tmp", path.file_name().unwrap_or("out")));
+	std::fs::write(&tmp_path, data)?;
+	std::fs::rename(&tmp_path, path)
+}
+
 pub fn ends_with_ignore_case(a: &dyn AsRef<str>, b: &dyn AsRef<str>) -> bool {
 	a.as_ref().ends_with(b.as_ref())
 		|| a.as_ref()
@@ -0,0 +1,164 @@
+//! An index of voiced lines, pairing each `OP_PLAY_VOICE` with the text it voices.
+//!
+//! The game plays a voice clip just before showing the line it belongs to, so each voice is
+//! matched with the next line of text in the same script, along with that line's speaker.
+
+use crate::opcodescript::{Op44Opcode, Opcode, Script};
+use crate::util::{csv_field, unescape_str};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VoiceLine {
+	pub script: usize,
+	/// Address of the `OP_PLAY_VOICE` opcode.
+	pub address: u32,
+	pub voice_type: u16,
+	pub voice_id: u16,
+	pub speaker: Option<String>,
+	pub speaker_translation: Option<String>,
+	/// Address of the voiced text, if there's any text after the voice at all.
+	pub text_address: Option<u32>,
+	pub text: String,
+	pub translation: Option<String>,
+}
+
+/// Lists every voice played in a script, in order, including those in inserted opcodes.
+pub fn voice_lines(script_idx: usize, script: &Script) -> Vec<VoiceLine> {
+	let mut opcodes = vec![];
+	flatten(&script.opcodes, &mut opcodes);
+
+	let mut lines = vec![];
+	let mut voice: Option<VoiceLine> = None;
+	let mut speaker = None;
+
+	for opcode in opcodes {
+		match opcode {
+			Opcode::OP_PLAY_VOICE(op) => {
+				// A voice that's played without any text in between is still listed, just
+				// without text.
+				lines.extend(voice.take());
+				voice = Some(new_voice_line(script_idx, op));
+			}
+			Opcode::OP_FREE_TEXT_OR_CHARNAME(op) if op.opt_arg2.is_none() => {
+				speaker = Some((op.unicode.clone(), op.translation.clone()));
+			}
+			Opcode::OP_46_LP_CHARNAME(op) | Opcode::OP_90_PHANTOM_CHARNAME(op) => {
+				speaker = Some((op.unicode.clone(), op.translation.clone()));
+			}
+			_ => {
+				let Some((address, text, translation)) = text_of(opcode) else {
+					continue;
+				};
+				let speaker = speaker.take();
+				let Some(mut line) = voice.take() else {
+					continue;
+				};
+
+				if let Some((unicode, translation)) = speaker {
+					line.speaker = Some(unicode);
+					line.speaker_translation = translation.as_deref().map(unescape_str);
+				}
+				line.text_address = Some(address);
+				line.text = text.to_string();
+				line.translation = translation.as_deref().map(unescape_str);
+				lines.push(line);
+			}
+		}
+	}
+
+	lines.extend(voice);
+	lines
+}
+
+fn new_voice_line(script_idx: usize, op: &Op44Opcode) -> VoiceLine {
+	VoiceLine {
+		script: script_idx,
+		address: op.address,
+		voice_type: op.arg1,
+		voice_id: op.arg2,
+		..Default::default()
+	}
+}
+
+fn flatten<'a>(opcodes: &'a [Opcode], output: &mut Vec<&'a Opcode>) {
+	for opcode in opcodes {
+		match opcode {
+			Opcode::OP_Insert(insert) => flatten(&insert.contents, output),
+			Opcode::OP_Replace(replace) => flatten(&replace.replacement, output),
+			Opcode::OP_Delete(_) => {}
+			_ => output.push(opcode),
+		}
+	}
+}
+
+/// Returns the address, text and translation of opcodes that show a line of text.
+pub(crate) fn text_of(opcode: &Opcode) -> Option<(u32, &str, &Option<String>)> {
+	match opcode {
+		Opcode::OP_TEXTBOX_DISPLAY(op) | Opcode::OP_SPECIAL_TEXT(op) => {
+			Some((op.address, &op.unicode, &op.translation))
+		}
+		Opcode::OP_47_TEXT(op) => Some((op.address, &op.unicode, &op.translation)),
+		Opcode::OP_FREE_TEXT_OR_CHARNAME(op) if op.opt_arg2.is_some() => {
+			Some((op.address, &op.unicode, &op.translation))
+		}
+		_ => None,
+	}
+}
+
+/// Renders the voice index as CSV, one row per voice.
+pub fn voice_csv(lines: &[VoiceLine]) -> String {
+	let mut output = String::from(
+		"voice_type,voice_id,speaker,speaker_translation,text,translation,script,address,text_address\n",
+	);
+
+	for line in lines {
+		output.push_str(&format!(
+			"0x{:04X},0x{:04X},{},{},{},{},{},0x{:08X},{}\n",
+			line.voice_type,
+			line.voice_id,
+			csv_field(line.speaker.as_deref().unwrap_or("")),
+			csv_field(line.speaker_translation.as_deref().unwrap_or("")),
+			csv_field(&line.text),
+			csv_field(line.translation.as_deref().unwrap_or("")),
+			line.script,
+			line.address,
+			line.text_address
+				.map(|it| format!("0x{it:08X}"))
+				.unwrap_or_default(),
+		));
+	}
+
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::voice_lines;
+	use crate::opcodescript::{Quirks, Script};
+
+	#[test]
+	fn test_voice_lines() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x44, 0x01, 0x00, 0x34, 0x12, // voice 0x1234
+			0x47, 0x0D, 0x00, b'M', 0x00, // speaker
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'A', 0x00,
+			0x45, 0xFF, 0xFF, 0x14, 0x00, b'B', 0x00, // not voiced
+			0x05, 0x00, // return
+		];
+		let (script, error) = Script::new(&data, Quirks::CCFC).unwrap();
+		assert!(error.is_none());
+
+		let lines = voice_lines(2, &script);
+		assert_eq!(lines.len(), 1);
+		assert_eq!(lines[0].voice_id, 0x1234);
+		assert_eq!(lines[0].speaker.as_deref(), Some("M"));
+		assert_eq!(lines[0].text, "A");
+		assert_eq!(lines[0].text_address, Some(0x0E));
+
+		let doc = crate::commands::tl_transform_script(&script);
+		assert!(
+			doc.contains("[voice @ 0x00000004]: type 0x0001, id 0x1234\n[speaker @ 0x00000009]")
+		);
+	}
+}
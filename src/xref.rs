@@ -0,0 +1,282 @@
+//! Cross-referencing of the script variables (or flags) that the scenario reads and writes.
+//!
+//! Variables are identified by the index they're stored at, which is all the scripts give us.
+//! A name map file can give them readable names as their purpose is worked out:
+//!
+//! ```text
+//! # Everything after a # is a comment.
+//! 0x0123 = met_misaki
+//! 292 = "saw_bad_end" # decimal indices and quoted names work too
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Context, Result};
+use camino::Utf8Path as Path;
+use once_cell::sync::Lazy;
+use tinyjson::JsonValue;
+
+use crate::opcodescript::{Opcode, Script};
+use crate::util::csv_field;
+
+/// Variable names from the name map set in `yeti.toml`, if there is one.
+pub static VARIABLE_NAMES: Lazy<HashMap<u16, String>> = Lazy::new(|| {
+	let Some(path) = &crate::config::get().variable_names else {
+		return HashMap::new();
+	};
+
+	load_variable_names(path).unwrap_or_else(|e| {
+		log::error!("{e:#}");
+		HashMap::new()
+	})
+});
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+	Read,
+	Write,
+	/// Reads the variable and writes back a new value based on it, like the arithmetic opcodes.
+	Update,
+}
+
+impl Access {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Access::Read => "read",
+			Access::Write => "write",
+			Access::Update => "update",
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableUse {
+	pub variable: u16,
+	pub script: usize,
+	pub address: u32,
+	/// The name of the opcode that uses the variable, as it appears in the yaml scripts.
+	pub opcode: &'static str,
+	pub access: Access,
+}
+
+/// Returns the variable index an opcode refers to, along with the opcode's name and how it uses
+/// the variable.
+fn variable_access(opcode: &Opcode) -> Option<(u16, &'static str, Access)> {
+	let access = match opcode {
+		Opcode::OP_10(op) => (op.arg1, "OP_10", Access::Write),
+		Opcode::OP_11(op) => (op.arg1, "OP_11", Access::Update),
+		Opcode::OP_12(op) => (op.arg1, "OP_12", Access::Update),
+		Opcode::OP_13(op) => (op.arg1, "OP_13", Access::Update),
+		Opcode::OP_14(op) => (op.arg1, "OP_14", Access::Update),
+		Opcode::OP_15(op) => (op.arg1, "OP_15", Access::Update),
+		Opcode::OP_16(op) => (op.arg1, "OP_16", Access::Update),
+		Opcode::OP_17(op) => (op.arg1, "OP_17", Access::Update),
+		Opcode::JE(op) => (op.arg1, "JE", Access::Read),
+		Opcode::JNE(op) => (op.arg1, "JNE", Access::Read),
+		Opcode::JG(op) => (op.arg1, "JG", Access::Read),
+		Opcode::JGE(op) => (op.arg1, "JGE", Access::Read),
+		Opcode::JL(op) => (op.arg1, "JL", Access::Read),
+		Opcode::JLE(op) => (op.arg1, "JLE", Access::Read),
+		Opcode::JZ(op) => (op.arg1, "JZ", Access::Read),
+		Opcode::JNZ(op) => (op.arg1, "JNZ", Access::Read),
+		Opcode::Switch(op) => (op.comparison_value, "Switch", Access::Read),
+		_ => return None,
+	};
+
+	Some(access)
+}
+
+/// Lists every variable use in a script, including those in inserted opcodes.
+pub fn variable_uses(script_idx: usize, script: &Script) -> Vec<VariableUse> {
+	fn walk(script_idx: usize, opcodes: &[Opcode], uses: &mut Vec<VariableUse>) {
+		for opcode in opcodes {
+			if let Opcode::OP_Insert(insert) = opcode {
+				walk(script_idx, &insert.contents, uses);
+			} else if let Opcode::OP_Replace(replace) = opcode {
+				walk(script_idx, &replace.replacement, uses);
+			} else if let Some((variable, name, access)) = variable_access(opcode) {
+				uses.push(VariableUse {
+					variable,
+					script: script_idx,
+					address: opcode.address(),
+					opcode: name,
+					access,
+				});
+			}
+		}
+	}
+
+	let mut uses = vec![];
+	walk(script_idx, &script.opcodes, &mut uses);
+	uses
+}
+
+/// Reads a variable name map. Keys are variable indices, in hex (`0x0123`) or decimal.
+pub fn load_variable_names(path: &Path) -> Result<HashMap<u16, String>> {
+	let data = std::fs::read_to_string(path).with_context(|| format!("Could not read {path}"))?;
+	parse_variable_names(&data).with_context(|| format!("Could not parse {path}"))
+}
+
+pub fn parse_variable_names(data: &str) -> Result<HashMap<u16, String>> {
+	data.lines()
+		.enumerate()
+		.map(|(idx, line)| (idx + 1, line.split('#').next().unwrap_or_default().trim()))
+		.filter(|(_, line)| !line.is_empty())
+		.map(|(line_no, line)| {
+			let (key, name) = line
+				.split_once('=')
+				.ok_or_else(|| anyhow!("Expected `index = name` on line {line_no}."))?;
+			let key = key.trim();
+			let index = match key.strip_prefix("0x").or_else(|| key.strip_prefix("0X")) {
+				Some(hex) => u16::from_str_radix(hex, 16),
+				None => key.parse(),
+			}
+			.map_err(|_| anyhow!("\"{key}\" on line {line_no} is not a valid variable index."))?;
+			Ok((index, name.trim().trim_matches('"').to_string()))
+		})
+		.collect()
+}
+
+/// Renders the cross-reference as JSON, one entry per variable in index order.
+pub fn xref_json(uses: &[VariableUse], names: &HashMap<u16, String>) -> String {
+	let mut by_variable: BTreeMap<u16, Vec<&VariableUse>> = BTreeMap::new();
+	for it in uses {
+		by_variable.entry(it.variable).or_default().push(it);
+	}
+
+	let variables = by_variable
+		.into_iter()
+		.map(|(variable, uses)| {
+			let mut entry = HashMap::new();
+			entry.insert(
+				"variable".to_string(),
+				JsonValue::String(format!("0x{variable:04X}")),
+			);
+			if let Some(name) = names.get(&variable) {
+				entry.insert("name".to_string(), JsonValue::String(name.clone()));
+			}
+			let uses = uses
+				.into_iter()
+				.map(|it| {
+					let mut entry = HashMap::new();
+					entry.insert("script".to_string(), JsonValue::Number(it.script as f64));
+					entry.insert(
+						"address".to_string(),
+						JsonValue::String(format!("0x{:08X}", it.address)),
+					);
+					entry.insert(
+						"opcode".to_string(),
+						JsonValue::String(it.opcode.to_string()),
+					);
+					entry.insert(
+						"access".to_string(),
+						JsonValue::String(it.access.as_str().to_string()),
+					);
+					JsonValue::Object(entry)
+				})
+				.collect();
+			entry.insert("uses".to_string(), JsonValue::Array(uses));
+			JsonValue::Object(entry)
+		})
+		.collect();
+
+	JsonValue::Array(variables).format().unwrap()
+}
+
+/// Renders the cross-reference as CSV, one row per use.
+pub fn xref_csv(uses: &[VariableUse], names: &HashMap<u16, String>) -> String {
+	let mut uses = uses.iter().collect::<Vec<_>>();
+	uses.sort_by_key(|it| (it.variable, it.script, it.address));
+
+	let mut output = String::from("variable,name,script,address,opcode,access\n");
+	for it in uses {
+		let name = names.get(&it.variable).map(String::as_str).unwrap_or("");
+		output.push_str(&format!(
+			"0x{:04X},{},{},0x{:08X},{},{}\n",
+			it.variable,
+			csv_field(name),
+			it.script,
+			it.address,
+			it.opcode,
+			it.access.as_str()
+		));
+	}
+
+	output
+}
+
+/// Adds a comment with the variable's name to every line of a yaml script that refers to a
+/// named variable.
+pub fn annotate_yaml(yaml: &str, names: &HashMap<u16, String>) -> String {
+	if names.is_empty() {
+		return yaml.to_string();
+	}
+
+	let mut output = String::with_capacity(yaml.len());
+	let mut current_opcode = "";
+	for line in yaml.lines() {
+		output.push_str(line);
+
+		let trimmed = line.trim_start();
+		if let Some(tag) = trimmed.strip_prefix("- !") {
+			current_opcode = tag;
+		}
+
+		let field = match current_opcode {
+			"Switch" => "comparison_value: ",
+			"OP_10" | "OP_11" | "OP_12" | "OP_13" | "OP_14" | "OP_15" | "OP_16" | "OP_17"
+			| "JE" | "JNE" | "JG" | "JGE" | "JL" | "JLE" | "JZ" | "JNZ" => "arg1: ",
+			_ => "",
+		};
+		let name = trimmed
+			.strip_prefix(field)
+			.filter(|_| !field.is_empty())
+			.and_then(|it| u16::from_str_radix(it.trim().trim_start_matches("0x"), 16).ok())
+			.and_then(|it| names.get(&it));
+		if let Some(name) = name {
+			output.push_str(" # ");
+			output.push_str(name);
+		}
+
+		output.push('\n');
+	}
+
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{annotate_yaml, parse_variable_names, variable_uses, xref_csv, Access};
+	use crate::opcodescript::{Quirks, Script};
+
+	#[test]
+	fn test_variable_xref() {
+		#[rustfmt::skip]
+		let data = [
+			0x04, 0x00, 0x00, 0x00, // header
+			0x10, 0x23, 0x01, 0x05, 0x00, // var 0x123 = 5
+			0x06, 0x23, 0x01, 0x05, 0x00, 0x04, 0x00, 0x00, 0x00, // je var 0x123, 5, 0x04
+			0x05, 0x00, // return
+		];
+		let (script, _) = Script::new(&data, Quirks::CCFC).unwrap();
+
+		let uses = variable_uses(3, &script);
+		assert_eq!(uses.len(), 2);
+		assert_eq!((uses[0].variable, uses[0].access), (0x123, Access::Write));
+		assert_eq!((uses[1].address, uses[1].access), (0x09, Access::Read));
+
+		let names =
+			parse_variable_names("0x0123 = met_misaki\n# comment\n5 = \"unused\" # also a comment")
+				.unwrap();
+		assert_eq!(names[&0x123], "met_misaki");
+		assert_eq!(names[&5], "unused");
+
+		assert_eq!(
+			xref_csv(&uses, &names).lines().nth(1),
+			Some("0x0123,met_misaki,3,0x00000004,OP_10,write")
+		);
+
+		let yaml = annotate_yaml(&crate::commands::script2yaml(&script), &names);
+		assert_eq!(yaml.matches("arg1: 0x0123 # met_misaki").count(), 2);
+	}
+}